@@ -1,9 +1,14 @@
 use clap::{Parser, Subcommand};
 use std::fs;
+use std::io::IsTerminal;
 
+use strata::cst;
 use strata::decode::decode;
+use strata::diagnostic::Diagnostic;
 use strata::encode::encode;
-use strata::parser::parse;
+use strata::error::ParseError;
+use strata::format::{format_document, format_value};
+use strata::parser::parse_recovering;
 
 #[derive(Parser)]
 #[command(name = "strata")]
@@ -34,49 +39,59 @@ enum Commands {
         input: String,
     },
 
-    /// Decode Strata bytecode (.scb) into a readable AST format
+    /// Decode Strata bytecode (.scb) and print it as Strata source (.st)
     Decode {
         /// Input Strata bytecode file (.scb)
         input: String,
     },
 
-    /// Format Strata source (.st) into a readable AST format
+    /// Rewrite Strata source (.st) in canonical formatting
     Fmt {
         /// Input Strata source file (.st)
         input: String,
+
+        /// Only check formatting; exit with 1 if the file would change
+        #[arg(long)]
+        check: bool,
     },
 }
 
 fn main() {
     let exit_code = match run() {
-        Ok(()) => 0,
+        Ok(code) => code,
         Err(err) => report_error(err),
     };
 
     std::process::exit(exit_code);
 }
 
-fn run() -> Result<(), strata::error::StrataError> {
+fn run() -> Result<i32, strata::error::StrataError> {
     let cli = Cli::parse();
 
     match cli.command {
         Commands::Compile { input, output } => {
             let source_text = fs::read_to_string(&input).map_err(strata::error::StrataError::Io)?;
 
-            let ast = parse(&source_text)?;
+            let ast = match parse_source(&input, &source_text) {
+                Ok(ast) => ast,
+                Err(code) => return Ok(code),
+            };
 
             let bytecode = encode(&ast)?;
 
             fs::write(&output, bytecode).map_err(strata::error::StrataError::Io)?;
 
-            Ok(())
+            Ok(0)
         }
 
         Commands::Hash { input } => {
             let bytecode = if input.ends_with(".st") {
                 let source_text =
                     fs::read_to_string(&input).map_err(strata::error::StrataError::Io)?;
-                let ast = parse(&source_text)?;
+                let ast = match parse_source(&input, &source_text) {
+                    Ok(ast) => ast,
+                    Err(code) => return Ok(code),
+                };
                 encode(&ast)?
             } else {
                 fs::read(&input).map_err(strata::error::StrataError::Io)?
@@ -85,36 +100,82 @@ fn run() -> Result<(), strata::error::StrataError> {
             let hash = blake3::hash(&bytecode);
             println!("{}", hash.to_hex());
 
-            Ok(())
+            Ok(0)
         }
         Commands::Decode { input } => {
             let bytecode = fs::read(&input).map_err(strata::error::StrataError::Io)?;
 
             let ast = decode(&bytecode)?;
 
-            println!("{:#?}", ast);
+            print!("{}", format_value(&ast));
 
-            Ok(())
+            Ok(0)
         }
-        Commands::Fmt { input } => {
+        Commands::Fmt { input, check } => {
             let source_text = fs::read_to_string(&input).map_err(strata::error::StrataError::Io)?;
 
-            let ast = parse(&source_text)?;
+            let document = match cst::parse(&source_text) {
+                Ok(document) => document,
+                Err(_) => {
+                    let errors = parse_recovering(&source_text).errors;
+                    return Ok(report_parse_errors(&input, &source_text, &errors));
+                }
+            };
+
+            let formatted = format_document(&document);
+
+            if formatted == source_text {
+                return Ok(0);
+            }
+
+            if check {
+                eprintln!("{}: not formatted", input);
+                return Ok(1);
+            }
 
-            println!("{:#?}", ast);
+            fs::write(&input, formatted).map_err(strata::error::StrataError::Io)?;
 
-            Ok(())
+            Ok(0)
         }
     }
 }
 
+// Parse errors are rendered against the source, so they are reported here
+// rather than in `report_error`. All errors in the file are shown at once.
+fn parse_source(input: &str, source: &str) -> Result<strata::value::Value, i32> {
+    let recovered = parse_recovering(source);
+
+    if recovered.errors.is_empty()
+        && let Some(value) = recovered.value
+    {
+        return Ok(value);
+    }
+
+    Err(report_parse_errors(input, source, &recovered.errors))
+}
+
+fn report_parse_errors(input: &str, source: &str, errors: &[ParseError]) -> i32 {
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let diagnostic = Diagnostic::new(input, source).color(color);
+
+    for err in errors {
+        eprintln!("{}", diagnostic.render(err));
+    }
+
+    if errors.len() > 1 {
+        eprintln!("error: {} parse errors", errors.len());
+    }
+
+    1
+}
+
 fn report_error(err: strata::error::StrataError) -> i32 {
     use strata::error::StrataError::*;
 
     match err {
         Parse(e) => {
             eprintln!("error: parse failed");
-            eprintln!("reason: {:?}", e.kind);
+            eprintln!("reason: {}", e.kind);
             eprintln!("line: {}", e.span.line);
             eprintln!("column: {}", e.span.column);
             1
@@ -122,14 +183,20 @@ fn report_error(err: strata::error::StrataError) -> i32 {
 
         Decode(e) => {
             eprintln!("error: decode failed");
-            eprintln!("reason: {:?}", e.kind);
+            eprintln!("reason: {}", e.kind);
             eprintln!("offset: {}", e.offset);
             1
         }
 
+        Frame(e) => {
+            eprintln!("error: framing failed");
+            eprintln!("reason: {}", e);
+            1
+        }
+
         Encode(e) => {
             eprintln!("error: encode failed");
-            eprintln!("reason: {:?}", e);
+            eprintln!("reason: {}", e);
             1
         }
 
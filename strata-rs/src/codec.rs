@@ -0,0 +1,93 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::decode::decode;
+use crate::encode::{encode_to_writer, encoded_len};
+use crate::error::{FrameError, StrataError};
+use crate::framing::{DEFAULT_MAX_FRAME_LEN, LEN_PREFIX, check_frame_len};
+use crate::value::Value;
+
+/// `tokio_util` codec for length-delimited Strata frames, the same wire
+/// format as `framing::FrameWriter` / `framing::FrameReader`.
+///
+/// Wrap an `AsyncRead` in `FramedRead::new(io, StrataCodec::new())` for a
+/// `Stream` of values, or an `AsyncWrite` in `FramedWrite` for a `Sink`.
+/// Payload decode error offsets count from the start of the frame payload.
+#[derive(Debug, Clone, Copy)]
+pub struct StrataCodec {
+    max_frame_len: usize,
+}
+
+impl StrataCodec {
+    pub fn new() -> Self {
+        Self {
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+}
+
+impl Default for StrataCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for StrataCodec {
+    type Item = Value;
+    type Error = StrataError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Value>, StrataError> {
+        if src.len() < LEN_PREFIX {
+            return Ok(None);
+        }
+
+        let prefix: [u8; LEN_PREFIX] = src[..LEN_PREFIX].try_into().unwrap();
+        let len = u32::from_be_bytes(prefix) as usize;
+        check_frame_len(len, self.max_frame_len)?;
+
+        if src.len() < LEN_PREFIX + len {
+            src.reserve(LEN_PREFIX + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(LEN_PREFIX);
+        let payload = src.split_to(len);
+
+        let value = decode(&payload).map_err(FrameError::Decode)?;
+        Ok(Some(value))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Value>, StrataError> {
+        match self.decode(src)? {
+            Some(value) => Ok(Some(value)),
+            None if src.is_empty() => Ok(None),
+            None => Err(FrameError::TruncatedFrame.into()),
+        }
+    }
+}
+
+impl Encoder<&Value> for StrataCodec {
+    type Error = StrataError;
+
+    fn encode(&mut self, value: &Value, dst: &mut BytesMut) -> Result<(), StrataError> {
+        let len = encoded_len(value);
+        check_frame_len(len, self.max_frame_len)?;
+
+        dst.reserve(LEN_PREFIX + len);
+        dst.put_u32(len as u32);
+        encode_to_writer(value, &mut dst.writer())
+    }
+}
+
+impl Encoder<Value> for StrataCodec {
+    type Error = StrataError;
+
+    fn encode(&mut self, value: Value, dst: &mut BytesMut) -> Result<(), StrataError> {
+        self.encode(&value, dst)
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use bytes::BytesMut;
+    use futures::{SinkExt, StreamExt};
+    use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
+
+    use crate::codec::StrataCodec;
+    use crate::error::{DecodeError, DecodeErrorKind, FrameError, StrataError};
+    use crate::framing::FrameWriter;
+    use crate::value::Value;
+
+    fn frame_err(result: Result<Option<Value>, StrataError>) -> FrameError {
+        match result {
+            Err(StrataError::Frame(e)) => e,
+            other => panic!("expected frame error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn codec_matches_frame_writer() {
+        let value = Value::List(vec![Value::Int(42), Value::String("hi".into())]);
+
+        let mut dst = BytesMut::new();
+        StrataCodec::new().encode(&value, &mut dst).unwrap();
+
+        let mut writer = FrameWriter::new(Vec::new());
+        writer.write_value(&value).unwrap();
+
+        assert_eq!(&dst[..], &writer.into_inner()[..]);
+    }
+
+    #[test]
+    fn codec_waits_for_partial_frames() {
+        let mut codec = StrataCodec::new();
+        let mut src = BytesMut::from(&[0u8, 0, 0][..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        src.extend_from_slice(&[2, 0x10]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        src.extend_from_slice(&[0x2a, 0, 0]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Value::Int(42)));
+        assert_eq!(&src[..], &[0, 0]);
+
+        assert_eq!(
+            frame_err(codec.decode_eof(&mut src)),
+            FrameError::TruncatedFrame
+        );
+    }
+
+    #[test]
+    fn codec_frame_limits() {
+        let mut codec = StrataCodec::new().max_frame_len(4);
+
+        let mut src = BytesMut::from(&[0u8, 0, 0, 0][..]);
+        assert_eq!(frame_err(codec.decode(&mut src)), FrameError::EmptyFrame);
+
+        let mut src = BytesMut::from(&[0u8, 0, 0, 9][..]);
+        assert_eq!(
+            frame_err(codec.decode(&mut src)),
+            FrameError::FrameTooLarge { len: 9, max: 4 }
+        );
+
+        let mut dst = BytesMut::new();
+        let err = codec
+            .encode(&Value::Bytes(vec![0; 8]), &mut dst)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            StrataError::Frame(FrameError::FrameTooLarge { len: 10, max: 4 })
+        ));
+    }
+
+    #[test]
+    fn codec_decode_error_offset_is_frame_relative() {
+        // second frame holds a truncated string
+        let mut src = BytesMut::from(&[0u8, 0, 0, 1, 0x00, 0, 0, 0, 3, 0x20, 0x05, b'h'][..]);
+        let mut codec = StrataCodec::new();
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Value::Null));
+        assert_eq!(
+            frame_err(codec.decode(&mut src)),
+            FrameError::Decode(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEOF,
+                offset: 2,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn codec_stream_and_sink() {
+        let values = vec![
+            Value::Int(42),
+            Value::String("hello".into()),
+            Value::Bytes(vec![0; 1000]),
+        ];
+
+        let (client, server) = tokio::io::duplex(64);
+
+        let mut sink = FramedWrite::new(client, StrataCodec::new());
+        let sent = values.clone();
+        let writer = tokio::spawn(async move {
+            for value in sent {
+                sink.send(value).await.unwrap();
+            }
+        });
+
+        let received: Vec<_> = FramedRead::new(server, StrataCodec::new())
+            .take(values.len())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        writer.await.unwrap();
+        assert_eq!(received, values);
+    }
+}
@@ -0,0 +1,367 @@
+//! Conversions between Rust types and `Value`.
+//!
+//! `From<T> for Value` and `TryFrom<Value> for T` are implemented for the
+//! standard types that have a Strata counterpart, and
+//! `#[derive(ToStrata, FromStrata)]` builds on them for structs and enums.
+//! The functions derived code calls live in a hidden module; they are
+//! public so the derives can reach them, and are not part of the API.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+
+use crate::error::{ConvertError, ConvertErrorKind};
+use crate::format::is_identifier;
+use crate::value::Value;
+
+use self::__private::{convert, element, expect_list, expect_map};
+
+/// A type that always converts to a map, and so can be flattened into its
+/// parent or be the content of an internally tagged variant.
+///
+/// `ToStrata` implements it for structs with named fields and for enums
+/// with a `tag`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not always convert to a map",
+    label = "flattened fields and internally tagged newtype variants must be maps"
+)]
+pub trait IntoMap {
+    fn into_map(self) -> BTreeMap<String, Value>;
+}
+
+impl ConvertError {
+    pub fn new(kind: ConvertErrorKind) -> Self {
+        Self {
+            kind,
+            path: String::new(),
+        }
+    }
+
+    pub fn invalid_type(expected: &'static str, found: &Value) -> Self {
+        Self::new(ConvertErrorKind::InvalidType {
+            expected,
+            found: found.kind().as_str(),
+        })
+    }
+
+    /// Prefix the path with a map key, as the error moves out of the
+    /// value stored under `key`.
+    pub fn in_field(mut self, key: &str) -> Self {
+        let key = if is_identifier(key) {
+            key.to_string()
+        } else {
+            format!("{:?}", key)
+        };
+
+        self.path = match self.path.chars().next() {
+            None => key,
+            Some('[') => key + &self.path,
+            Some(_) => format!("{}.{}", key, self.path),
+        };
+        self
+    }
+
+    /// Prefix the path with a list index.
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.path = match self.path.chars().next() {
+            None | Some('[') => format!("[{}]{}", index, self.path),
+            Some(_) => format!("[{}].{}", index, self.path),
+        };
+        self
+    }
+}
+
+// converting a `Value` into a `Value` cannot fail
+impl From<Infallible> for ConvertError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(ConvertError::invalid_type("bool", &other)),
+        }
+    }
+}
+
+macro_rules! int_conversions {
+    // types that always fit in i64 convert both ways
+    (lossless: $($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::Int(value.into())
+            }
+        }
+
+        int_conversions!(checked: $ty);
+    )*};
+
+    (checked: $($ty:ty),*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = ConvertError;
+
+            fn try_from(value: Value) -> Result<Self, ConvertError> {
+                match value {
+                    Value::Int(number) => <$ty>::try_from(number).map_err(|_| {
+                        ConvertError::new(ConvertErrorKind::IntegerOutOfRange(
+                            number,
+                            stringify!($ty),
+                        ))
+                    }),
+                    other => Err(ConvertError::invalid_type("int", &other)),
+                }
+            }
+        }
+    )*};
+}
+
+int_conversions!(lossless: i8, i16, i32, i64, u8, u16, u32);
+int_conversions!(checked: u64, usize);
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::String(string) => Ok(string),
+            other => Err(ConvertError::invalid_type("string", &other)),
+        }
+    }
+}
+
+/// `None` is Null.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// A `Vec` is a List, so `Vec<u8>` is a list of integers; use
+/// `Value::Bytes` for bytes.
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value>,
+    ConvertError: From<T::Error>,
+{
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, ConvertError> {
+        expect_list(value)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| element(item, index))
+            .collect()
+    }
+}
+
+impl<T: Into<Value>> IntoMap for BTreeMap<String, T> {
+    fn into_map(self) -> BTreeMap<String, Value> {
+        self.into_iter().map(|(k, v)| (k, v.into())).collect()
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(value: BTreeMap<String, T>) -> Self {
+        Value::Map(value.into_map())
+    }
+}
+
+impl<T> TryFrom<Value> for BTreeMap<String, T>
+where
+    T: TryFrom<Value>,
+    ConvertError: From<T::Error>,
+{
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, ConvertError> {
+        expect_map(value)?
+            .into_iter()
+            .map(|(key, item)| {
+                let converted = convert(item).map_err(|err| err.in_field(&key))?;
+                Ok((key, converted))
+            })
+            .collect()
+    }
+}
+
+impl<T: Into<Value>> IntoMap for HashMap<String, T> {
+    fn into_map(self) -> BTreeMap<String, Value> {
+        self.into_iter().map(|(k, v)| (k, v.into())).collect()
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(value: HashMap<String, T>) -> Self {
+        Value::Map(value.into_map())
+    }
+}
+
+impl<T> TryFrom<Value> for HashMap<String, T>
+where
+    T: TryFrom<Value>,
+    ConvertError: From<T::Error>,
+{
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, ConvertError> {
+        expect_map(value)?
+            .into_iter()
+            .map(|(key, item)| {
+                let converted = convert(item).map_err(|err| err.in_field(&key))?;
+                Ok((key, converted))
+            })
+            .collect()
+    }
+}
+
+/// Support for derived code.
+#[doc(hidden)]
+pub mod __private {
+    use std::collections::BTreeMap;
+
+    use crate::error::{ConvertError, ConvertErrorKind};
+    use crate::value::Value;
+
+    pub fn convert<T>(value: Value) -> Result<T, ConvertError>
+    where
+        T: TryFrom<Value>,
+        ConvertError: From<T::Error>,
+    {
+        T::try_from(value).map_err(ConvertError::from)
+    }
+
+    pub fn expect_map(value: Value) -> Result<BTreeMap<String, Value>, ConvertError> {
+        match value {
+            Value::Map(map) => Ok(map),
+            other => Err(ConvertError::invalid_type("map", &other)),
+        }
+    }
+
+    pub fn expect_list(value: Value) -> Result<Vec<Value>, ConvertError> {
+        match value {
+            Value::List(items) => Ok(items),
+            other => Err(ConvertError::invalid_type("list", &other)),
+        }
+    }
+
+    pub fn expect_len(value: Value, len: usize) -> Result<Vec<Value>, ConvertError> {
+        let items = expect_list(value)?;
+        if items.len() != len {
+            return Err(ConvertError::new(ConvertErrorKind::InvalidLength {
+                expected: len,
+                found: items.len(),
+            }));
+        }
+        Ok(items)
+    }
+
+    pub fn expect_null(value: Value) -> Result<(), ConvertError> {
+        match value {
+            Value::Null => Ok(()),
+            other => Err(ConvertError::invalid_type("null", &other)),
+        }
+    }
+
+    pub fn element<T>(value: Value, index: usize) -> Result<T, ConvertError>
+    where
+        T: TryFrom<Value>,
+        ConvertError: From<T::Error>,
+    {
+        convert(value).map_err(|err| err.at_index(index))
+    }
+
+    /// Remove and convert a required field.
+    pub fn take_field<T>(map: &mut BTreeMap<String, Value>, key: &str) -> Result<T, ConvertError>
+    where
+        T: TryFrom<Value>,
+        ConvertError: From<T::Error>,
+    {
+        match map.remove(key) {
+            Some(value) => convert(value).map_err(|err| err.in_field(key)),
+            None => Err(ConvertError::new(ConvertErrorKind::MissingField).in_field(key)),
+        }
+    }
+
+    /// Remove and convert an optional field; missing and Null are `None`.
+    pub fn take_optional<T>(
+        map: &mut BTreeMap<String, Value>,
+        key: &str,
+    ) -> Result<Option<T>, ConvertError>
+    where
+        T: TryFrom<Value>,
+        ConvertError: From<T::Error>,
+    {
+        match map.remove(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => convert(value).map(Some).map_err(|err| err.in_field(key)),
+        }
+    }
+
+    /// Merge a flattened field's map, or an internally tagged variant's
+    /// content, into its parent's. Keys already set by the parent's other
+    /// fields or by the tag are kept, which is also where reading the value
+    /// back would put them.
+    pub fn flatten_into(map: &mut BTreeMap<String, Value>, entries: BTreeMap<String, Value>) {
+        for (key, value) in entries {
+            map.entry(key).or_insert(value);
+        }
+    }
+
+    /// The variant name of an externally tagged enum and its content: a bare
+    /// string for unit variants, otherwise a map with one entry.
+    pub fn external_variant(value: Value) -> Result<(String, Option<Value>), ConvertError> {
+        match value {
+            Value::String(name) => Ok((name, None)),
+            Value::Map(map) if map.len() == 1 => {
+                let (name, content) = map.into_iter().next().unwrap();
+                Ok((name, Some(content)))
+            }
+            other => Err(ConvertError::invalid_type(
+                "variant name or map with one entry",
+                &other,
+            )),
+        }
+    }
+
+    /// The content of a variant that has fields, which a bare name lacks.
+    pub fn variant_content(content: Option<Value>) -> Result<Value, ConvertError> {
+        content.ok_or_else(|| {
+            ConvertError::new(ConvertErrorKind::InvalidType {
+                expected: "map",
+                found: "string",
+            })
+        })
+    }
+
+    pub fn unknown_variant(name: String) -> ConvertError {
+        ConvertError::new(ConvertErrorKind::UnknownVariant(name))
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::convert::__private::convert;
+    use crate::error::{ConvertError, ConvertErrorKind};
+    use crate::value::Value;
+    use crate::{bytes, int, list, map, null, string};
+
+    #[test]
+    fn scalars_convert_both_ways() {
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(-3i8), int!(-3));
+        assert_eq!(Value::from(u32::MAX), int!(u32::MAX as i64));
+        assert_eq!(Value::from("api"), string!("api"));
+        assert_eq!(Value::from(None::<i64>), null!());
+        assert_eq!(Value::from(Some(1u8)), int!(1));
+
+        assert!(bool::try_from(Value::Bool(false)).is_ok_and(|b| !b));
+        assert_eq!(u64::try_from(int!(i64::MAX)), Ok(i64::MAX as u64));
+        assert_eq!(String::try_from(string!("api")), Ok("api".to_string()));
+        assert_eq!(convert::<Value>(bytes!(vec![1])), Ok(bytes!(vec![1])));
+    }
+
+    #[test]
+    fn integers_are_range_checked() {
+        let err = u8::try_from(int!(256)).unwrap_err();
+        assert_eq!(err.kind, ConvertErrorKind::IntegerOutOfRange(256, "u8"));
+        assert_eq!(err.to_string(), "integer 256 out of range for u8");
+        assert_eq!(err.code(), "convert.integer_out_of_range");
+
+        assert!(u64::try_from(int!(-1)).is_err());
+        assert!(i32::try_from(int!(i64::MIN)).is_err());
+    }
+
+    #[test]
+    fn type_mismatch_names_both_kinds() {
+        let err = i64::try_from(string!("1")).unwrap_err();
+        assert_eq!(
+            err.kind,
+            ConvertErrorKind::InvalidType {
+                expected: "int",
+                found: "string"
+            }
+        );
+        assert_eq!(err.to_string(), "expected int, found string");
+        assert_eq!(err.code(), "convert.invalid_type");
+
+        // bytes are not a list of integers
+        assert_eq!(
+            Vec::<u8>::try_from(bytes!(vec![1]))
+                .unwrap_err()
+                .to_string(),
+            "expected list, found bytes"
+        );
+    }
+
+    #[test]
+    fn collections() {
+        let ports = vec![80u16, 443];
+        assert_eq!(Value::from(ports.clone()), list![int!(80), int!(443)]);
+        assert_eq!(Vec::<u16>::try_from(list![int!(80), int!(443)]), Ok(ports));
+
+        let counts = HashMap::from([("b".to_string(), 2u8), ("a".to_string(), 1)]);
+        let value = map! { "a" => int!(1), "b" => int!(2) };
+        assert_eq!(Value::from(counts.clone()), value);
+        assert_eq!(HashMap::<String, u8>::try_from(value.clone()), Ok(counts));
+        assert_eq!(BTreeMap::<String, Value>::try_from(value).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn paths_follow_nesting() {
+        let value = map! {
+            "groups" => list![
+                map! { "my port" => list![int!(1)] },
+                map! { "my port" => list![int!(1), string!("2")] },
+            ],
+        };
+
+        let err = BTreeMap::<String, Vec<BTreeMap<String, Vec<u8>>>>::try_from(value).unwrap_err();
+        assert_eq!(err.path, "groups[1].\"my port\"[1]");
+        assert_eq!(
+            err.to_string(),
+            "expected int, found string at `groups[1].\"my port\"[1]`"
+        );
+
+        let err = ConvertError::new(ConvertErrorKind::MissingField)
+            .in_field("b")
+            .at_index(0)
+            .in_field("a");
+        assert_eq!(err.path, "a[0].b");
+    }
+}
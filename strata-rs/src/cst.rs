@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::{ParseError, Span, SpanRange};
+use crate::format::{format_node, key_token, line_break};
+use crate::lexer::TokenKind;
+use crate::parser;
+use crate::value::Value;
+
+/// Lossless syntax tree of a Strata Text document.
+///
+/// Every token keeps the whitespace and comments in front of it, so
+/// `to_string()` reproduces the source byte for byte. Edits through
+/// [`Document::set`], [`Document::insert`] and [`Document::remove`] only
+/// touch the affected entries; comments, layout and entry order elsewhere
+/// are kept.
+///
+/// Spans refer to the source that was parsed. After an edit they are
+/// stale: re-parse the written text for fresh ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    pub root: Node,
+    /// Carries the trivia after the last token.
+    pub eof: SyntaxToken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    /// The token exactly as written.
+    pub text: String,
+    /// Whitespace and comments between the previous token and this one.
+    pub leading_trivia: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Scalar(SyntaxToken),
+    List(List),
+    Map(Map),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct List {
+    pub open: SyntaxToken,
+    pub items: Vec<Item>,
+    pub close: SyntaxToken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub value: Node,
+    pub comma: Option<SyntaxToken>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Map {
+    /// `None` only for the `key { ... }` shorthand, which is a map of one
+    /// entry without braces of its own.
+    pub open: Option<SyntaxToken>,
+    pub entries: Vec<Entry>,
+    pub close: Option<SyntaxToken>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: SyntaxToken,
+    /// `None` for a `key { ... }` entry.
+    pub colon: Option<SyntaxToken>,
+    pub value: Node,
+    pub comma: Option<SyntaxToken>,
+}
+
+/// One step of a path into a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for Segment<'a> {
+    fn from(key: &'a str) -> Self {
+        Segment::Key(key)
+    }
+}
+
+impl From<usize> for Segment<'_> {
+    fn from(index: usize) -> Self {
+        Segment::Index(index)
+    }
+}
+
+/// Parse Strata Text into a lossless syntax tree.
+///
+/// This is the grammar [`crate::parser::parse`] uses, so it accepts the
+/// same input, fails with the same errors, and `parse(source)?.to_value()`
+/// is the value `parser::parse` returns.
+pub fn parse(source: &str) -> Result<Document, ParseError> {
+    parser::parse_document(source)
+}
+
+impl SyntaxToken {
+    pub(crate) fn new(kind: TokenKind, text: &str, leading_trivia: &str) -> Self {
+        Self {
+            kind,
+            text: text.to_string(),
+            leading_trivia: leading_trivia.to_string(),
+            span: Span {
+                offset: 0,
+                line: 0,
+                column: 0,
+            },
+        }
+    }
+
+    /// Comments in the leading trivia, each without its line break.
+    ///
+    /// A comment at the end of a line belongs to the token that follows
+    /// it.
+    pub fn comments(&self) -> impl Iterator<Item = &str> {
+        // trivia is only whitespace and comments, and a comment runs to the
+        // end of its line
+        self.leading_trivia
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+    }
+
+    /// The token without its trivia. Tokens never span lines.
+    pub fn range(&self) -> SpanRange {
+        SpanRange {
+            start: self.span,
+            end: Span {
+                offset: self.span.offset + self.text.len(),
+                line: self.span.line,
+                column: self.span.column + self.text.chars().count(),
+            },
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str(&self.leading_trivia);
+        out.push_str(&self.text);
+    }
+}
+
+impl Entry {
+    pub fn key(&self) -> &str {
+        match &self.key.kind {
+            TokenKind::Ident(name) | TokenKind::String(name) => name,
+            _ => unreachable!("map keys are identifiers or strings"),
+        }
+    }
+}
+
+impl Node {
+    pub fn to_value(&self) -> Value {
+        match self {
+            Node::Scalar(token) => match &token.kind {
+                TokenKind::Null => Value::Null,
+                TokenKind::True => Value::Bool(true),
+                TokenKind::False => Value::Bool(false),
+                TokenKind::Int(number) => Value::Int(*number),
+                TokenKind::String(string) => Value::String(string.clone()),
+                TokenKind::Bytes(bytes) => Value::Bytes(bytes.clone()),
+                _ => unreachable!("scalar nodes hold literal tokens"),
+            },
+
+            Node::List(list) => Value::List(
+                list.items
+                    .iter()
+                    .map(|item| item.value.to_value())
+                    .collect(),
+            ),
+
+            // later duplicates win, as in `parser::parse`
+            Node::Map(map) => Value::Map(
+                map.entries
+                    .iter()
+                    .map(|entry| (entry.key().to_string(), entry.value.to_value()))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+        }
+    }
+
+    pub fn first_token(&self) -> &SyntaxToken {
+        match self {
+            Node::Scalar(token) => token,
+            Node::List(list) => &list.open,
+            Node::Map(map) => map.open.as_ref().unwrap_or_else(|| &map.entries[0].key),
+        }
+    }
+
+    pub fn last_token(&self) -> &SyntaxToken {
+        match self {
+            Node::Scalar(token) => token,
+            Node::List(list) => &list.close,
+            Node::Map(map) => match &map.close {
+                Some(close) => close,
+                None => map.entries.last().unwrap().value.last_token(),
+            },
+        }
+    }
+
+    /// From the first token to the end of the last, without trivia.
+    pub fn range(&self) -> SpanRange {
+        SpanRange {
+            start: self.first_token().span,
+            end: self.last_token().range().end,
+        }
+    }
+
+    /// The node at `path` below this one. A key step picks the last entry
+    /// with that key, which is the one whose value the document has.
+    pub fn get(&self, path: &[Segment]) -> Option<&Node> {
+        let Some((step, rest)) = path.split_first() else {
+            return Some(self);
+        };
+
+        match (self, step) {
+            (Node::Map(map), Segment::Key(key)) => map
+                .entries
+                .iter()
+                .rev()
+                .find(|entry| entry.key() == *key)?
+                .value
+                .get(rest),
+            (Node::List(list), Segment::Index(index)) => list.items.get(*index)?.value.get(rest),
+            _ => None,
+        }
+    }
+
+    // the node at `path` and how many brackets enclose it
+    fn get_mut(&mut self, path: &[Segment], depth: usize) -> Option<(&mut Node, usize)> {
+        let Some((step, rest)) = path.split_first() else {
+            return Some((self, depth));
+        };
+
+        let depth = depth + self.bracket_depth();
+
+        match (self, step) {
+            (Node::Map(map), Segment::Key(key)) => map
+                .entries
+                .iter_mut()
+                .rev()
+                .find(|entry| entry.key() == *key)?
+                .value
+                .get_mut(rest, depth),
+            (Node::List(list), Segment::Index(index)) => {
+                list.items.get_mut(*index)?.value.get_mut(rest, depth)
+            }
+            _ => None,
+        }
+    }
+
+    // a shorthand map adds no indentation level of its own
+    fn bracket_depth(&self) -> usize {
+        match self {
+            Node::Map(Map { open: None, .. }) => 0,
+            _ => 1,
+        }
+    }
+
+    // every token in source order
+    pub(crate) fn visit<'a>(&'a self, f: &mut impl FnMut(&'a SyntaxToken)) {
+        match self {
+            Node::Scalar(token) => f(token),
+
+            Node::List(list) => {
+                f(&list.open);
+                for item in &list.items {
+                    item.value.visit(f);
+                    if let Some(comma) = &item.comma {
+                        f(comma);
+                    }
+                }
+                f(&list.close);
+            }
+
+            Node::Map(map) => {
+                if let Some(open) = &map.open {
+                    f(open);
+                }
+                for entry in &map.entries {
+                    f(&entry.key);
+                    if let Some(colon) = &entry.colon {
+                        f(colon);
+                    }
+                    entry.value.visit(f);
+                    if let Some(comma) = &entry.comma {
+                        f(comma);
+                    }
+                }
+                if let Some(close) = &map.close {
+                    f(close);
+                }
+            }
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        self.visit(&mut |token| token.write(out));
+    }
+}
+
+impl Document {
+    pub fn to_value(&self) -> Value {
+        self.root.to_value()
+    }
+
+    pub fn get(&self, path: &[Segment]) -> Option<&Node> {
+        self.root.get(path)
+    }
+
+    /// Whether any token, or the end of input, has a comment in front of
+    /// it.
+    pub fn has_comments(&self) -> bool {
+        let mut found = self.eof.comments().next().is_some();
+        self.root.visit(&mut |token| {
+            found |= token.comments().next().is_some();
+        });
+        found
+    }
+
+    /// Replace the value at `path`, keeping the comments in front of it.
+    ///
+    /// Returns `false` if there is nothing at `path`.
+    pub fn set(&mut self, path: &[Segment], value: &Value) -> bool {
+        let Some((last, parent)) = path.split_last() else {
+            let trivia = self.root.first_token().leading_trivia.clone();
+            self.root = format_node(value, 0, trivia);
+            return true;
+        };
+
+        let Some((node, depth)) = self.root.get_mut(parent, 0) else {
+            return false;
+        };
+        let depth = depth + node.bracket_depth();
+
+        match (node, last) {
+            (Node::Map(map), Segment::Key(key)) => {
+                if !map.entries.iter().any(|entry| entry.key() == *key) {
+                    return false;
+                }
+
+                // `key: value` cannot stand without braces around it
+                let depth = if map.open.is_none() && !matches!(value, Value::Map(_)) {
+                    add_braces(map, depth);
+                    depth + 1
+                } else {
+                    depth
+                };
+
+                let entry = map
+                    .entries
+                    .iter_mut()
+                    .rev()
+                    .find(|entry| entry.key() == *key)
+                    .unwrap();
+
+                let trivia = match entry.colon {
+                    Some(_) => entry.value.first_token().leading_trivia.clone(),
+                    None => " ".to_string(),
+                };
+
+                entry.value = format_node(value, depth, trivia);
+
+                // only a map may follow its key directly
+                if entry.colon.is_none() && !matches!(value, Value::Map(_)) {
+                    entry.colon = Some(SyntaxToken::new(TokenKind::Colon, ":", ""));
+                }
+                true
+            }
+
+            (Node::List(list), Segment::Index(index)) => {
+                let Some(item) = list.items.get_mut(*index) else {
+                    return false;
+                };
+
+                let trivia = item.value.first_token().leading_trivia.clone();
+                item.value = format_node(value, depth, trivia);
+                true
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Set `key` in the map at `path`. An existing entry is updated in
+    /// place; a new one is appended after the last entry, following its
+    /// layout.
+    ///
+    /// Returns `false` if there is no map at `path`.
+    pub fn insert(&mut self, path: &[Segment], key: &str, value: &Value) -> bool {
+        let Some((node, depth)) = self.root.get_mut(path, 0) else {
+            return false;
+        };
+
+        let Node::Map(map) = node else {
+            return false;
+        };
+
+        if map.entries.iter().any(|entry| entry.key() == key) {
+            let mut entry_path = path.to_vec();
+            entry_path.push(Segment::Key(key));
+            return self.set(&entry_path, value);
+        }
+
+        if map.open.is_none() {
+            add_braces(map, depth);
+        }
+
+        // new entries follow the layout of the last one
+        let trivia = match map.entries.last() {
+            Some(entry) => match entry.key.leading_trivia.rfind('\n') {
+                Some(newline) => entry.key.leading_trivia[newline..].to_string(),
+                None => " ".to_string(),
+            },
+            None => " ".to_string(),
+        };
+
+        if !trivia.contains('\n')
+            && let Some(last) = map.entries.last_mut()
+            && last.comma.is_none()
+        {
+            last.comma = Some(SyntaxToken::new(TokenKind::Comma, ",", ""));
+        }
+
+        let close = map.close.as_mut().unwrap();
+        if map.entries.is_empty() && close.leading_trivia.is_empty() {
+            close.leading_trivia.push(' ');
+        }
+
+        map.entries.push(Entry {
+            key: key_token(key, trivia),
+            colon: Some(SyntaxToken::new(TokenKind::Colon, ":", "")),
+            value: format_node(value, depth + 1, " ".to_string()),
+            comma: None,
+        });
+        true
+    }
+
+    /// Remove the entry or element at `path` together with the comments in
+    /// front of it. Every entry with the key is removed, so an earlier
+    /// duplicate cannot take its place.
+    ///
+    /// Returns `false` if there is nothing at `path`.
+    pub fn remove(&mut self, path: &[Segment]) -> bool {
+        let Some((last, parent)) = path.split_last() else {
+            return false;
+        };
+
+        let Some((node, _)) = self.root.get_mut(parent, 0) else {
+            return false;
+        };
+
+        match (node, last) {
+            // a shorthand map cannot be left without entries
+            (Node::Map(map), Segment::Key(key)) if map.open.is_some() => {
+                let before = map.entries.len();
+                map.entries.retain(|entry| entry.key() != *key);
+                map.entries.len() != before
+            }
+
+            (Node::List(list), Segment::Index(index)) if *index < list.items.len() => {
+                list.items.remove(*index);
+                true
+            }
+
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.root.write(&mut out);
+        self.eof.write(&mut out);
+        f.write_str(&out)
+    }
+}
+
+// `key { ... }` becomes `{ key { ... } }` so entries can be added; the
+// map sits `depth` brackets deep
+fn add_braces(map: &mut Map, depth: usize) {
+    let trivia = std::mem::take(&mut map.entries[0].key.leading_trivia);
+
+    map.open = Some(SyntaxToken::new(TokenKind::LBrace, "{", &trivia));
+    map.entries[0].key.leading_trivia = line_break(depth + 1);
+    map.close = Some(SyntaxToken::new(TokenKind::RBrace, "}", &line_break(depth)));
+}
@@ -0,0 +1,324 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use proptest::prelude::*;
+
+    use crate::cst::{self, Node, Segment};
+    use crate::format::format_value;
+    use crate::parser;
+    use crate::test_strategies::arb_value;
+    use crate::value::Value;
+    use crate::{int, list, map, string};
+
+    const CONFIG: &str = r#"# service settings
+service {
+  // how often to retry
+  retries: 3   # per request
+  timeout:30,
+
+  "content-type": "text/plain"
+  ports: [ 80, 443, ]   // public
+  limits { burst: 0x0a, key: b64"3q0=" }
+}
+// end
+"#;
+
+    fn assert_lossless(source: &str) {
+        let document = cst::parse(source).unwrap();
+
+        assert_eq!(document.to_string(), source);
+        assert_eq!(document.to_value(), parser::parse(source).unwrap());
+    }
+
+    #[test]
+    fn roundtrip_is_lossless() {
+        assert_lossless(CONFIG);
+        assert_lossless("  42  ");
+        assert_lossless("{}");
+        assert_lossless("[\n]\n\n");
+        assert_lossless("{ a: 1, a: 2 }");
+        assert_lossless("[a { b { } }, \"é\\u{1F600}\"]");
+    }
+
+    #[test]
+    fn roundtrip_all_vectors() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("vectors");
+
+        for version in ["v1", "v2"] {
+            for entry in fs::read_dir(root.join(version)).unwrap() {
+                let path = entry.unwrap().path();
+
+                if path.extension().is_some_and(|ext| ext == "st") {
+                    assert_lossless(&fs::read_to_string(&path).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors_match_parser() {
+        for source in [
+            "{ retries 3 }",
+            "[1 2]",
+            "{ a: 1 ]",
+            "config",
+            "1 2",
+            "\"x\\q\"",
+        ] {
+            assert_eq!(
+                cst::parse(source).unwrap_err(),
+                parser::parse(source).unwrap_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn comments_belong_to_next_token() {
+        let document = cst::parse(CONFIG).unwrap();
+
+        let Node::Map(root) = &document.root else {
+            panic!("root is a shorthand map");
+        };
+        assert_eq!(
+            root.entries[0].key.comments().collect::<Vec<_>>(),
+            vec!["# service settings"]
+        );
+
+        let Node::Map(service) = &root.entries[0].value else {
+            panic!("service is a map");
+        };
+        let comments: Vec<Vec<&str>> = service
+            .entries
+            .iter()
+            .map(|entry| entry.key.comments().collect())
+            .collect();
+
+        assert_eq!(
+            comments,
+            vec![
+                vec!["// how often to retry"],
+                vec!["# per request"],
+                vec![],
+                vec![],
+                vec!["// public"],
+            ]
+        );
+        assert_eq!(document.eof.comments().collect::<Vec<_>>(), vec!["// end"]);
+    }
+
+    #[test]
+    fn has_comments_looks_at_every_token() {
+        assert!(cst::parse(CONFIG).unwrap().has_comments());
+        assert!(cst::parse("[1, # one\n 2]").unwrap().has_comments());
+        assert!(cst::parse("{ a: 1 }\n// end\n").unwrap().has_comments());
+        assert!(
+            !cst::parse("{ a: \"# not a comment\" }")
+                .unwrap()
+                .has_comments()
+        );
+    }
+
+    #[test]
+    fn get_follows_keys_and_indexes() {
+        let document = cst::parse(CONFIG).unwrap();
+
+        let port = document.get(&["service".into(), "ports".into(), 1.into()]);
+        assert_eq!(port.unwrap().to_value(), int!(443));
+
+        let burst = document.get(&[
+            Segment::Key("service"),
+            Segment::Key("limits"),
+            Segment::Key("burst"),
+        ]);
+        assert_eq!(burst.unwrap().first_token().text, "0x0a");
+
+        assert!(
+            document
+                .get(&["service".into(), "ports".into(), 2.into()])
+                .is_none()
+        );
+        assert!(document.get(&["service".into(), 0.into()]).is_none());
+        assert!(document.get(&["missing".into()]).is_none());
+    }
+
+    #[test]
+    fn set_keeps_comments_and_layout() {
+        let mut document = cst::parse(CONFIG).unwrap();
+
+        assert!(document.set(&["service".into(), "retries".into()], &int!(5)));
+        assert!(document.set(&["service".into(), "ports".into(), 0.into()], &int!(8080)));
+        assert!(!document.set(&["service".into(), "missing".into()], &int!(1)));
+
+        assert_eq!(
+            document.to_string(),
+            CONFIG
+                .replace("retries: 3", "retries: 5")
+                .replace("[ 80,", "[ 8080,")
+        );
+    }
+
+    #[test]
+    fn set_writes_containers_at_their_depth() {
+        let source = "{\n  a: 1 // one\n  b { c: 2 }\n}\n";
+        let mut document = cst::parse(source).unwrap();
+
+        document.set(
+            &["a".into()],
+            &map! { "x" => list![int!(1), map! { "y" => int!(2) }] },
+        );
+        document.set(&["b".into()], &string!("flat"));
+
+        let written = document.to_string();
+        assert_eq!(
+            written,
+            "{\n  a: {\n    x: [\n      1,\n      {\n        y: 2\n      }\n    ]\n  } // one\n  b: \"flat\"\n}\n"
+        );
+        assert_eq!(
+            parser::parse(&written).unwrap(),
+            map! {
+                "a" => map! { "x" => list![int!(1), map! { "y" => int!(2) }] },
+                "b" => string!("flat"),
+            }
+        );
+    }
+
+    #[test]
+    fn set_scalar_on_shorthand_adds_braces() {
+        let mut document = cst::parse("# top\nconfig { a: 1 }\n").unwrap();
+        assert!(document.set(&["config".into()], &int!(5)));
+        assert_eq!(document.to_string(), "# top\n{\n  config: 5\n}\n");
+
+        let mut document = cst::parse("[a { x: 1 }]").unwrap();
+        assert!(document.set(&[0.into(), "a".into()], &list![]));
+        assert_eq!(document.to_string(), "[{\n    a: []\n  }]");
+
+        // a map still follows its key directly
+        let mut document = cst::parse("config { a: 1 }").unwrap();
+        assert!(document.set(&["config".into()], &map! { "b" => int!(2) }));
+        assert_eq!(document.to_string(), "config {\n  b: 2\n}");
+    }
+
+    // after every edit the written document parses to the edited value
+    fn assert_reparses(document: &cst::Document) {
+        let written = document.to_string();
+        let reparsed = parser::parse(&written).unwrap_or_else(|e| panic!("{} in\n{}", e, written));
+        assert_eq!(reparsed, document.to_value(), "in\n{}", written);
+    }
+
+    #[test]
+    fn edits_always_reparse() {
+        let values = [
+            int!(1),
+            string!("a \"b\""),
+            list![],
+            list![int!(1), list![]],
+            map! {},
+            map! { "content-type" => map! { "x" => Value::Bytes(vec![]) } },
+        ];
+
+        for source in [CONFIG, "service { a { b: 1 } }", "[a { x: 1 }, [1, 2]]"] {
+            for value in &values {
+                let mut document = cst::parse(source).unwrap();
+
+                for path in [
+                    vec![],
+                    vec![Segment::Key("service")],
+                    vec![Segment::Key("service"), Segment::Key("retries")],
+                    vec![Segment::Key("service"), Segment::Key("limits")],
+                    vec![Segment::Key("service"), Segment::Key("a")],
+                    vec![Segment::Index(0), Segment::Key("a")],
+                    vec![Segment::Index(1), Segment::Index(0)],
+                ] {
+                    let mut document = document.clone();
+                    document.set(&path, value);
+                    assert_reparses(&document);
+
+                    document.insert(&path, "new key", value);
+                    assert_reparses(&document);
+
+                    document.remove(&path);
+                    assert_reparses(&document);
+                }
+
+                document.set(&[Segment::Key("service")], value);
+                assert_reparses(&document);
+            }
+        }
+    }
+
+    #[test]
+    fn insert_follows_existing_layout() {
+        let mut document = cst::parse("{\n  // first\n  a: 1\n}\n").unwrap();
+        document.insert(&[], "content-type", &string!("json"));
+        assert_eq!(
+            document.to_string(),
+            "{\n  // first\n  a: 1\n  \"content-type\": \"json\"\n}\n"
+        );
+
+        let mut document = cst::parse("{ a: 1 }").unwrap();
+        document.insert(&[], "b", &int!(2));
+        assert_eq!(document.to_string(), "{ a: 1, b: 2 }");
+
+        let mut document = cst::parse("{ list: [{}] }").unwrap();
+        document.insert(&["list".into(), 0.into()], "k", &int!(1));
+        assert_eq!(document.to_string(), "{ list: [{ k: 1 }] }");
+
+        // existing keys are updated in place
+        let mut document = cst::parse("{ a: 1, b: 2 }").unwrap();
+        document.insert(&[], "a", &int!(3));
+        assert_eq!(document.to_string(), "{ a: 3, b: 2 }");
+
+        assert!(!document.insert(&["a".into()], "x", &int!(1)));
+    }
+
+    #[test]
+    fn insert_into_shorthand_root_adds_braces() {
+        let mut document = cst::parse("# top\nserver { port: 80 }\n").unwrap();
+        document.insert(&[], "debug", &int!(1));
+
+        let written = document.to_string();
+        assert_eq!(written, "# top\n{\n  server { port: 80 }\n  debug: 1\n}\n");
+        assert_eq!(
+            parser::parse(&written).unwrap(),
+            map! { "server" => map! { "port" => int!(80) }, "debug" => int!(1) }
+        );
+    }
+
+    #[test]
+    fn remove_drops_entry_and_its_comments() {
+        let source = "{\n  // keep\n  a: 1\n  // old setting\n  b: 2\n  c: [1, 2, 3]\n}\n";
+        let mut document = cst::parse(source).unwrap();
+
+        assert!(document.remove(&["b".into()]));
+        assert!(document.remove(&["c".into(), 2.into()]));
+        assert!(!document.remove(&["b".into()]));
+        assert!(!document.remove(&[]));
+
+        assert_eq!(
+            document.to_string(),
+            "{\n  // keep\n  a: 1\n  c: [1, 2,]\n}\n"
+        );
+
+        // duplicates go together, so no earlier value resurfaces
+        let mut document = cst::parse("{ a: 1, a: 2, b: 3 }").unwrap();
+        document.remove(&["a".into()]);
+        assert_eq!(document.to_value(), map! { "b" => int!(3) });
+    }
+
+    proptest! {
+        #[test]
+        fn formatted_values_roundtrip(value in arb_value()) {
+            let text = format_value(&value);
+            let document = cst::parse(&text).unwrap();
+
+            prop_assert_eq!(document.to_string(), text);
+            prop_assert_eq!(document.to_value(), value);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, Error as _, IntoDeserializer, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::decode::decode_canonical;
+use crate::error::SerdeError;
+use crate::value::Value;
+
+/// Deserialize a `T` from Strata Core Binary.
+///
+/// The input is decoded with `decode::decode_canonical`, so it must be
+/// exactly what `ser::to_vec` writes for some value, then read with the
+/// same mapping. Nothing is coerced, except that Bytes read as a
+/// sequence of integers: a type mismatch, an integer that does not fit
+/// the target type, or a list of the wrong length for a tuple is an error.
+///
+/// Nesting is limited to `DecodeLimits::DEFAULT_MAX_DEPTH`, although
+/// `to_vec` writes any depth. To read deeper trusted input, decode it with
+/// `decode::decode_with_options` and pass the result to `from_value`.
+pub fn from_slice<T: DeserializeOwned>(input: &[u8]) -> Result<T, SerdeError> {
+    from_value(decode_canonical(input)?)
+}
+
+/// Deserialize a `T` from a `Value` with the same mapping as `from_slice`.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, SerdeError> {
+    T::deserialize(value)
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(b) => Unexpected::Bool(*b),
+        Value::Int(number) => Unexpected::Signed(*number),
+        Value::String(string) => Unexpected::Str(string),
+        Value::Bytes(bytes) => Unexpected::Bytes(bytes),
+        Value::List(_) => Unexpected::Seq,
+        Value::Map(_) => Unexpected::Map,
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(items: Vec<Value>, visitor: V) -> Result<V::Value, SerdeError> {
+    let mut seq = SeqDeserializer::new(items.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    // every item must be used, so [1, 2, 3] is not a (i64, i64)
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, V: Visitor<'de>>(
+    map: BTreeMap<String, Value>,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    let mut entries = MapDeserializer::new(map.into_iter());
+    let value = visitor.visit_map(&mut entries)?;
+    entries.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = SerdeError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(number) => visitor.visit_i64(number),
+            Value::String(string) => visitor.visit_string(string),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::List(items) => visit_list(items, visitor),
+            Value::Map(map) => visit_map(map, visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, _: V) -> Result<V::Value, SerdeError> {
+        Err(SerdeError::UnsupportedType("f32"))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, _: V) -> Result<V::Value, SerdeError> {
+        Err(SerdeError::UnsupportedType("f64"))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Bytes also read as a sequence of integers, so a buffer written
+    /// with `serde_bytes` can be read into a `Vec<u8>`. This applies to
+    /// every sequence and tuple, whatever its element type.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::Bytes(bytes) => visit_list(
+                bytes
+                    .into_iter()
+                    .map(|byte| Value::Int(byte.into()))
+                    .collect(),
+                visitor,
+            ),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::String(name) => visitor.visit_enum(Variant { name, value: None }),
+            Value::Map(map) if map.len() == 1 => {
+                let (name, value) = map.into_iter().next().unwrap();
+                visitor.visit_enum(Variant {
+                    name,
+                    value: Some(value),
+                })
+            }
+            other => Err(SerdeError::invalid_type(
+                unexpected(&other),
+                &"a variant name or a map with one entry",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct tuple_struct map struct identifier
+    }
+}
+
+impl IntoDeserializer<'_, SerdeError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// An enum variant: a bare name for unit variants, otherwise the single
+/// entry of a map.
+struct Variant {
+    name: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = SerdeError;
+    type Variant = Content;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Content), SerdeError> {
+        let name: StringDeserializer<SerdeError> = self.name.into_deserializer();
+        Ok((seed.deserialize(name)?, Content(self.value)))
+    }
+}
+
+struct Content(Option<Value>);
+
+impl<'de> VariantAccess<'de> for Content {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.0 {
+            None | Some(Value::Null) => Ok(()),
+            Some(other) => Err(SerdeError::invalid_type(
+                unexpected(&other),
+                &"unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, SerdeError> {
+        match self.0 {
+            Some(value) => seed.deserialize(value),
+            None => Err(SerdeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            Some(value) => de::Deserializer::deserialize_seq(value, visitor),
+            None => Err(SerdeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            Some(value) => de::Deserializer::deserialize_any(value, visitor),
+            None => Err(SerdeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
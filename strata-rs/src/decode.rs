@@ -1,60 +1,371 @@
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
 use crate::error::{DecodeError, DecodeErrorKind};
-use crate::value::Value;
+use crate::value::{Value, ValueRef};
+
+/// Resource limits applied while decoding untrusted input.
+///
+/// `max_total_bytes` counts string/bytes payloads plus the slots
+/// reserved for list items and map entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum number of nested lists/maps. Scalars at the root have depth 0.
+    pub max_depth: usize,
+    /// Maximum length of a single string or bytes payload.
+    pub max_length: usize,
+    /// Maximum element count of a single list or map.
+    pub max_elements: usize,
+    /// Maximum total bytes allocated for the decoded value.
+    pub max_total_bytes: usize,
+}
 
-pub struct Decoder<'a> {
-    input: &'a [u8],
-    offset: usize,
+impl DecodeLimits {
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    /// No limits at all, including nesting depth.
+    pub const fn unlimited() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_length: usize::MAX,
+            max_elements: usize::MAX,
+            max_total_bytes: usize::MAX,
+        }
+    }
 }
 
-impl<'a> Decoder<'a> {
-    fn new(input: &'a [u8]) -> Self {
-        Self { input, offset: 0 }
+/// Only nesting depth is bounded by default. Payloads cannot be longer
+/// than the input, but the decoded value can still take many times its
+/// size; set `max_total_bytes` to bound that.
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            ..Self::unlimited()
+        }
     }
+}
 
-    fn err(&self, kind: DecodeErrorKind) -> DecodeError {
-        DecodeError {
-            kind,
-            offset: self.offset,
+/// How `decode_with_options`, `decode_ref_with_options` and
+/// `StreamDecoder::with_options` read their input.
+///
+/// The default accepts non-canonical input under `DecodeLimits::default()`,
+/// which is what `decode` uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Reject input that is not canonical `.scb` (see `decode_canonical`).
+    pub canonical: bool,
+    pub limits: DecodeLimits,
+}
+
+/// Tracks nesting depth and allocation against `DecodeLimits`.
+struct Budget {
+    limits: DecodeLimits,
+    depth: usize,
+    allocated: usize,
+}
+
+impl Budget {
+    fn new(limits: DecodeLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    fn charge(&mut self, bytes: usize, offset: usize) -> Result<(), DecodeError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        if self.allocated > self.limits.max_total_bytes {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::AllocationLimitExceeded,
+                offset,
+            });
         }
+        Ok(())
     }
 
+    /// String/bytes payload length, read at `offset`.
+    fn length(&mut self, len: usize, offset: usize) -> Result<(), DecodeError> {
+        if len > self.limits.max_length {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::LengthLimitExceeded,
+                offset,
+            });
+        }
+        self.charge(len, offset)
+    }
+
+    /// List/map element count read at `offset`, each element reserving `slot` bytes.
+    fn count(&mut self, count: usize, slot: usize, offset: usize) -> Result<(), DecodeError> {
+        if count > self.limits.max_elements {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::ElementLimitExceeded,
+                offset,
+            });
+        }
+        self.charge(count.saturating_mul(slot), offset)
+    }
+
+    fn enter(&mut self, tag_offset: usize) -> Result<(), DecodeError> {
+        if self.depth >= self.limits.max_depth {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::DepthLimitExceeded,
+                offset: tag_offset,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+/// Where a `Decoder` reads its bytes from: an in-memory slice here, or a
+/// `std::io::Read` in `stream`.
+pub(crate) trait Source {
+    /// A string or bytes payload, borrowed from the input where possible.
+    type Chunk;
+    type Error: From<DecodeError>;
+
+    /// Number of bytes consumed so far.
+    fn offset(&self) -> usize;
+
+    /// `UnexpectedEOF` at the current offset when the input is exhausted.
+    fn read_byte(&mut self) -> Result<u8, Self::Error>;
+
+    /// `UnexpectedEOF` at the current offset when fewer than `len` bytes
+    /// remain.
+    fn read_chunk(&mut self, len: usize) -> Result<Self::Chunk, Self::Error>;
+
+    /// How many of `count` list items to reserve room for up front.
+    fn capacity(&self, count: usize) -> usize;
+}
+
+/// Most list items reserved before any are read; longer lists grow as
+/// items arrive.
+const MAX_RESERVED_ITEMS: usize = 64;
+
+/// An in-memory `.scb` buffer.
+pub(crate) struct SliceSource<'a> {
+    input: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SliceSource<'a> {
     fn remaining(&self) -> usize {
         self.input.len() - self.offset
     }
+}
+
+impl<'a> Source for SliceSource<'a> {
+    type Chunk = &'a [u8];
+    type Error = DecodeError;
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
 
     fn read_byte(&mut self) -> Result<u8, DecodeError> {
-        if self.offset >= self.input.len() {
-            return Err(self.err(DecodeErrorKind::UnexpectedEOF));
-        }
-        let byte = self.input[self.offset];
+        let byte = *self.input.get(self.offset).ok_or(DecodeError {
+            kind: DecodeErrorKind::UnexpectedEOF,
+            offset: self.offset,
+        })?;
         self.offset += 1;
         Ok(byte)
     }
 
-    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+    fn read_chunk(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
         if self.remaining() < len {
-            return Err(self.err(DecodeErrorKind::UnexpectedEOF));
+            return Err(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEOF,
+                offset: self.offset,
+            });
         }
-        let slice = &self.input[self.offset..self.offset + len];
+        let chunk = &self.input[self.offset..self.offset + len];
         self.offset += len;
-        Ok(slice)
+        Ok(chunk)
+    }
+
+    // every item takes at least one byte, so never reserve more than the
+    // input could possibly hold, and never more than a few slots: nested
+    // lists would otherwise each reserve the rest of the input
+    fn capacity(&self, count: usize) -> usize {
+        count.min(self.remaining()).min(MAX_RESERVED_ITEMS)
+    }
+}
+
+/// What a `Decoder` builds from payloads of type `C`: `Value` from any
+/// source, or `ValueRef` borrowing from a slice.
+pub(crate) trait Tree<C>: Sized {
+    type Key: Ord;
+
+    fn null() -> Self;
+    fn bool(b: bool) -> Self;
+    fn int(n: i64) -> Self;
+    /// `None` when the payload is not UTF-8.
+    fn string(chunk: C) -> Option<Self>;
+    fn bytes(chunk: C) -> Self;
+    fn list(items: Vec<Self>) -> Self;
+    fn map(entries: BTreeMap<Self::Key, Self>) -> Self;
+    /// The key of a map entry, if this is a string.
+    fn into_key(self) -> Option<Self::Key>;
+}
+
+impl<C: Into<Vec<u8>>> Tree<C> for Value {
+    type Key = String;
+
+    fn null() -> Self {
+        Value::Null
+    }
+
+    fn bool(b: bool) -> Self {
+        Value::Bool(b)
+    }
+
+    fn int(n: i64) -> Self {
+        Value::Int(n)
+    }
+
+    fn string(chunk: C) -> Option<Self> {
+        String::from_utf8(chunk.into()).ok().map(Value::String)
+    }
+
+    fn bytes(chunk: C) -> Self {
+        Value::Bytes(chunk.into())
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        Value::List(items)
+    }
+
+    fn map(entries: BTreeMap<String, Self>) -> Self {
+        Value::Map(entries)
+    }
+
+    fn into_key(self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Tree<&'a [u8]> for ValueRef<'a> {
+    type Key = &'a str;
+
+    fn null() -> Self {
+        ValueRef::Null
+    }
+
+    fn bool(b: bool) -> Self {
+        ValueRef::Bool(b)
+    }
+
+    fn int(n: i64) -> Self {
+        ValueRef::Int(n)
     }
 
-    fn decode_uleb128(&mut self) -> Result<u64, DecodeError> {
+    fn string(chunk: &'a [u8]) -> Option<Self> {
+        std::str::from_utf8(chunk).ok().map(ValueRef::String)
+    }
+
+    fn bytes(chunk: &'a [u8]) -> Self {
+        ValueRef::Bytes(chunk)
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        ValueRef::List(items)
+    }
+
+    fn map(entries: BTreeMap<&'a str, Self>) -> Self {
+        ValueRef::Map(entries)
+    }
+
+    fn into_key(self) -> Option<&'a str> {
+        match self {
+            ValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// The one `.scb` decoder, generic over where bytes come from and what is
+/// built from them.
+pub(crate) struct Decoder<S> {
+    source: S,
+    canonical: bool,
+    limits: DecodeLimits,
+    budget: Budget,
+}
+
+impl<S: Source> Decoder<S> {
+    pub(crate) fn new(source: S, options: DecodeOptions) -> Self {
+        Self {
+            source,
+            canonical: options.canonical,
+            limits: options.limits,
+            budget: Budget::new(options.limits),
+        }
+    }
+
+    pub(crate) fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Start a fresh budget, so limits apply to each value separately.
+    pub(crate) fn reset_budget(&mut self) {
+        self.budget = Budget::new(self.limits);
+    }
+
+    pub(crate) fn err(&self, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            kind,
+            offset: self.source.offset(),
+        }
+    }
+
+    // In canonical mode the varint must be the shortest encoding of its value
+    fn check_varint(
+        &self,
+        start: usize,
+        bytes: &[u8],
+        is_canonical: fn(&[u8]) -> bool,
+    ) -> Result<(), DecodeError> {
+        if self.canonical && !is_canonical(bytes) {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::NonCanonicalVarint,
+                offset: start,
+            });
+        }
+        Ok(())
+    }
+
+    fn decode_uleb128(&mut self) -> Result<u64, S::Error> {
+        let start = self.source.offset();
+        let mut seen = [0u8; 10];
         let mut result = 0u64;
         let mut shift = 0;
 
         loop {
-            let byte = self.read_byte()?;
+            let byte = self.source.read_byte()?;
             let value = (byte & 0x7F) as u64;
 
             if shift >= 64 {
-                return Err(self.err(DecodeErrorKind::InvalidVarint));
+                return Err(self.err(DecodeErrorKind::InvalidVarint).into());
             }
 
+            seen[shift / 7] = byte;
             result |= value << shift;
 
             if (byte & 0x80) == 0 {
+                self.check_varint(start, &seen[..=shift / 7], is_canonical_uleb128)?;
                 return Ok(result);
             }
 
@@ -62,16 +373,18 @@ impl<'a> Decoder<'a> {
         }
     }
 
-    fn decode_sleb128(&mut self) -> Result<i64, DecodeError> {
+    fn decode_sleb128(&mut self) -> Result<i64, S::Error> {
+        let start = self.source.offset();
+        let mut seen = [0u8; 10];
         let mut result = 0i64;
         let mut shift = 0;
         let mut byte;
 
         loop {
-            byte = self.read_byte()? as i64;
-            let value = byte & 0x7F;
+            byte = self.source.read_byte()?;
+            seen[shift / 7] = byte;
 
-            result |= value << shift;
+            result |= ((byte & 0x7F) as i64) << shift;
             shift += 7;
 
             if (byte & 0x80) == 0 {
@@ -79,7 +392,7 @@ impl<'a> Decoder<'a> {
             }
 
             if shift >= 64 {
-                return Err(self.err(DecodeErrorKind::InvalidVarint));
+                return Err(self.err(DecodeErrorKind::InvalidVarint).into());
             }
         }
 
@@ -87,79 +400,201 @@ impl<'a> Decoder<'a> {
             result |= (!0i64) << shift;
         }
 
+        self.check_varint(start, &seen[..shift / 7], is_canonical_sleb128)?;
         Ok(result)
     }
 
-    pub fn decode_value(&mut self) -> Result<Value, DecodeError> {
-        let tag = self.read_byte()?;
+    // string/bytes payload, and the offset it starts at
+    fn decode_payload(&mut self) -> Result<(S::Chunk, usize), S::Error> {
+        let start = self.source.offset();
+        let len = self.decode_uleb128()? as usize;
+        self.budget.length(len, start)?;
+
+        let payload_start = self.source.offset();
+        Ok((self.source.read_chunk(len)?, payload_start))
+    }
+
+    // list/map element count, each element reserving `slot` bytes
+    fn decode_count(&mut self, slot: usize) -> Result<usize, S::Error> {
+        let start = self.source.offset();
+        let count = self.decode_uleb128()? as usize;
+        self.budget.count(count, slot, start)?;
+        Ok(count)
+    }
+
+    pub(crate) fn decode_tree<T: Tree<S::Chunk>>(&mut self) -> Result<T, S::Error> {
+        let tag_offset = self.source.offset();
+        let tag = self.source.read_byte()?;
 
         match tag {
-            0x00 => Ok(Value::Null),
-            0x01 => Ok(Value::Bool(false)),
-            0x02 => Ok(Value::Bool(true)),
+            0x00 => Ok(T::null()),
+            0x01 => Ok(T::bool(false)),
+            0x02 => Ok(T::bool(true)),
 
-            0x10 => {
-                let n = self.decode_sleb128()?;
-                Ok(Value::Int(n))
-            }
+            0x10 => Ok(T::int(self.decode_sleb128()?)),
 
             0x20 => {
-                let len = self.decode_uleb128()? as usize;
-                let start = self.offset;
-                let bytes = self.read_slice(len)?;
-                let s = std::str::from_utf8(bytes).map_err(|_| DecodeError {
-                    kind: DecodeErrorKind::InvalidUtf8,
-                    offset: start,
-                })?;
-
-                Ok(Value::String(s.to_string()))
+                let (chunk, start) = self.decode_payload()?;
+                T::string(chunk).ok_or_else(|| {
+                    DecodeError {
+                        kind: DecodeErrorKind::InvalidUtf8,
+                        offset: start,
+                    }
+                    .into()
+                })
             }
 
-            0x21 => {
-                let len = self.decode_uleb128()? as usize;
-                let bytes = self.read_slice(len)?;
-                Ok(Value::Bytes(bytes.to_vec()))
-            }
+            0x21 => Ok(T::bytes(self.decode_payload()?.0)),
 
             0x30 => {
-                let count = self.decode_uleb128()? as usize;
-                let mut items = Vec::with_capacity(count);
+                self.budget.enter(tag_offset)?;
+                let count = self.decode_count(size_of::<T>())?;
+
+                let mut items = Vec::with_capacity(self.source.capacity(count));
                 for _ in 0..count {
-                    items.push(self.decode_value()?);
+                    items.push(self.decode_tree()?);
                 }
-                Ok(Value::List(items))
+
+                self.budget.leave();
+                Ok(T::list(items))
             }
 
             0x40 => {
-                let count = self.decode_uleb128()? as usize;
-                let mut map = std::collections::BTreeMap::new();
+                self.budget.enter(tag_offset)?;
+                let count = self.decode_count(size_of::<(T::Key, T)>())?;
+                let mut map = BTreeMap::new();
 
                 for _ in 0..count {
-                    let key = match self.decode_value()? {
-                        Value::String(s) => s,
-                        _ => return Err(self.err(DecodeErrorKind::InvalidTag(tag))),
+                    let key_start = self.source.offset();
+                    let key = match self.decode_tree::<T>()?.into_key() {
+                        Some(key) => key,
+                        None => return Err(self.err(DecodeErrorKind::InvalidTag(tag)).into()),
                     };
-                    let value = self.decode_value()?;
+                    check_key_order(self.canonical, &map, &key, key_start)?;
+
+                    let value = self.decode_tree()?;
                     map.insert(key, value);
                 }
-                Ok(Value::Map(map))
+
+                self.budget.leave();
+                Ok(T::map(map))
             }
 
-            other => Err(self.err(DecodeErrorKind::InvalidTag(other))),
+            other => Err(self.err(DecodeErrorKind::InvalidTag(other)).into()),
         }
     }
 }
 
-pub fn decode(input: &[u8]) -> Result<Value, DecodeError> {
-    let mut decoder = Decoder::new(input);
-    let value = decoder.decode_value()?;
+impl<'a> Decoder<SliceSource<'a>> {
+    fn for_slice(input: &'a [u8], options: DecodeOptions) -> Self {
+        Self::new(SliceSource { input, offset: 0 }, options)
+    }
 
-    if decoder.remaining() != 0 {
+    /// Decode one value that must span the whole input.
+    fn decode_all<T: Tree<&'a [u8]>>(&mut self) -> Result<T, DecodeError> {
+        let value = self.decode_tree()?;
+        if self.source.remaining() != 0 {
+            return Err(self.err(DecodeErrorKind::TrailingBytes));
+        }
+        Ok(value)
+    }
+}
+
+/// Canonical maps are strictly sorted by UTF-8 bytes; `key` starts at `key_start`.
+fn check_key_order<K: Ord, V>(
+    canonical: bool,
+    map: &BTreeMap<K, V>,
+    key: &K,
+    key_start: usize,
+) -> Result<(), DecodeError> {
+    if canonical
+        && let Some((last, _)) = map.last_key_value()
+        && key <= last
+    {
+        let kind = if map.contains_key(key) {
+            DecodeErrorKind::DuplicateMapKey
+        } else {
+            DecodeErrorKind::UnsortedMapKeys
+        };
         return Err(DecodeError {
-            kind: DecodeErrorKind::TrailingBytes,
-            offset: decoder.offset,
+            kind,
+            offset: key_start,
         });
     }
+    Ok(())
+}
+
+/// A canonical ULEB128 has no redundant trailing zero group and fits in u64.
+fn is_canonical_uleb128(bytes: &[u8]) -> bool {
+    match bytes {
+        [_] => true,
+        [.., last] => *last != 0x00 && (bytes.len() < 10 || *last == 0x01),
+        [] => false,
+    }
+}
+
+/// A canonical SLEB128 has no redundant trailing sign group and fits in i64.
+fn is_canonical_sleb128(bytes: &[u8]) -> bool {
+    match bytes {
+        [_] => true,
+        [.., prev, last] => {
+            let sign_set = prev & 0x40 != 0;
+            let redundant = (*last == 0x00 && !sign_set) || (*last == 0x7F && sign_set);
+            !redundant && (bytes.len() < 10 || *last == 0x00 || *last == 0x7F)
+        }
+        [] => false,
+    }
+}
+
+/// Decode a single value that must span the whole input.
+///
+/// Nesting is limited to `DecodeLimits::DEFAULT_MAX_DEPTH` (256) lists and
+/// maps, so deeply nested input fails with `DepthLimitExceeded` instead of
+/// overflowing the stack. Earlier releases had no depth limit; use
+/// `decode_with_limits(input, DecodeLimits::unlimited())` for that
+/// behaviour. Non-canonical input is accepted, see `decode_canonical`.
+pub fn decode(input: &[u8]) -> Result<Value, DecodeError> {
+    decode_with_options(input, DecodeOptions::default())
+}
+
+/// Decode and additionally require the input to be canonical `.scb`.
+///
+/// On top of the regular checks, this rejects over-long varints, unsorted
+/// map keys and duplicate map keys. A successful result guarantees
+/// `encode(&decode_canonical(input)?) == input`.
+pub fn decode_canonical(input: &[u8]) -> Result<Value, DecodeError> {
+    decode_with_options(
+        input,
+        DecodeOptions {
+            canonical: true,
+            ..DecodeOptions::default()
+        },
+    )
+}
+
+/// Decode untrusted input under explicit resource limits.
+pub fn decode_with_limits(input: &[u8], limits: DecodeLimits) -> Result<Value, DecodeError> {
+    decode_with_options(
+        input,
+        DecodeOptions {
+            limits,
+            ..DecodeOptions::default()
+        },
+    )
+}
+
+pub fn decode_with_options(input: &[u8], options: DecodeOptions) -> Result<Value, DecodeError> {
+    Decoder::for_slice(input, options).decode_all()
+}
+
+/// Decode without copying: strings and bytes borrow from `input`.
+pub fn decode_ref(input: &[u8]) -> Result<ValueRef<'_>, DecodeError> {
+    decode_ref_with_options(input, DecodeOptions::default())
+}
 
-    Ok(value)
+pub fn decode_ref_with_options(
+    input: &[u8],
+    options: DecodeOptions,
+) -> Result<ValueRef<'_>, DecodeError> {
+    Decoder::for_slice(input, options).decode_all()
 }
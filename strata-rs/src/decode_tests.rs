@@ -1,8 +1,12 @@
 #[cfg(test)]
 mod tests {
-    use crate::decode::decode;
+    use crate::decode::{
+        DecodeLimits, DecodeOptions, decode, decode_canonical, decode_ref, decode_ref_with_options,
+        decode_with_limits,
+    };
+    use crate::encode::encode;
     use crate::error::DecodeErrorKind;
-    use crate::value::Value;
+    use crate::value::{Value, ValueRef};
 
     // null/bool/int
     #[test]
@@ -114,4 +118,220 @@ mod tests {
         assert_eq!(err.kind, DecodeErrorKind::TrailingBytes);
         assert_eq!(err.offset, 1);
     }
+
+    // canonical mode
+    #[test]
+    fn decode_canonical_accepts_encoder_output() {
+        for n in [0, 1, -1, 63, -64, 64, -65, 127, 128, i64::MAX, i64::MIN] {
+            let value = Value::List(vec![Value::Int(n), Value::Bytes(vec![0; 200])]);
+            let bytes = encode(&value).unwrap();
+            assert_eq!(decode_canonical(&bytes), Ok(value));
+        }
+    }
+
+    #[test]
+    fn decode_canonical_rejects_overlong_uleb128() {
+        // "hi" with length 2 encoded as 0x82 0x00
+        let bytes = vec![0x20, 0x82, 0x00, b'h', b'i'];
+
+        assert_eq!(decode(&bytes), Ok(Value::String("hi".into())));
+
+        let err = decode_canonical(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::NonCanonicalVarint);
+        assert_eq!(err.offset, 1);
+    }
+
+    #[test]
+    fn decode_canonical_rejects_overlong_sleb128() {
+        // Int 1 as 0x81 0x00, Int -1 as 0xFF 0x7F
+        for bytes in [vec![0x10, 0x81, 0x00], vec![0x10, 0xFF, 0x7F]] {
+            assert!(decode(&bytes).is_ok());
+
+            let err = decode_canonical(&bytes).unwrap_err();
+            assert_eq!(err.kind, DecodeErrorKind::NonCanonicalVarint);
+            assert_eq!(err.offset, 1);
+        }
+    }
+
+    #[test]
+    fn decode_canonical_rejects_unsorted_keys() {
+        // { 'b': 1, 'a': 2 }
+        let bytes = vec![
+            0x40, 0x02, // map with 2 entries
+            0x20, 0x01, b'b', 0x10, 0x01, // 'b': 1
+            0x20, 0x01, b'a', 0x10, 0x02, // 'a': 2
+        ];
+
+        assert!(decode(&bytes).is_ok());
+
+        let err = decode_canonical(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnsortedMapKeys);
+        assert_eq!(err.offset, 7);
+    }
+
+    #[test]
+    fn decode_canonical_rejects_duplicate_keys() {
+        // { 'a': 1, 'a': 2 }
+        let bytes = vec![
+            0x40, 0x02, // map with 2 entries
+            0x20, 0x01, b'a', 0x10, 0x01, // 'a': 1
+            0x20, 0x01, b'a', 0x10, 0x02, // 'a': 2
+        ];
+
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("a".into(), Value::Int(2));
+        assert_eq!(decode(&bytes), Ok(Value::Map(map)));
+
+        let err = decode_canonical(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::DuplicateMapKey);
+        assert_eq!(err.offset, 7);
+    }
+
+    // resource limits
+    #[test]
+    fn decode_huge_count_does_not_preallocate() {
+        // list claiming u64::MAX items with no items present
+        let mut bytes = vec![0x30];
+        bytes.extend_from_slice(&[0xFF; 9]);
+        bytes.push(0x01);
+
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnexpectedEOF);
+    }
+
+    #[test]
+    fn decode_nested_huge_counts_do_not_preallocate() {
+        // 200 lists each claiming 2^40 items, nested, then 64 KiB of nulls
+        let mut bytes = [0x30, 0x80, 0x80, 0x80, 0x80, 0x80, 0x20].repeat(200);
+        bytes.resize(bytes.len() + 64 * 1024, 0x00);
+
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnexpectedEOF);
+    }
+
+    #[test]
+    fn decode_default_depth_limit() {
+        // 100k nested single-item lists
+        let bytes = [0x30, 0x01].repeat(100_000);
+
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::DepthLimitExceeded);
+        assert_eq!(err.offset, 2 * DecodeLimits::DEFAULT_MAX_DEPTH);
+    }
+
+    #[test]
+    fn decode_depth_limit() {
+        let limits = DecodeLimits {
+            max_depth: 1,
+            ..DecodeLimits::unlimited()
+        };
+
+        // [[]]
+        let err = decode_with_limits(&[0x30, 0x01, 0x30, 0x00], limits).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::DepthLimitExceeded);
+        assert_eq!(err.offset, 2);
+
+        assert_eq!(
+            decode_with_limits(&[0x30, 0x00], limits),
+            Ok(Value::List(vec![]))
+        );
+    }
+
+    #[test]
+    fn decode_length_limit() {
+        let limits = DecodeLimits {
+            max_length: 1,
+            ..DecodeLimits::unlimited()
+        };
+
+        let err = decode_with_limits(&[0x21, 0x02, 0xaa, 0xbb], limits).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::LengthLimitExceeded);
+        assert_eq!(err.offset, 1);
+    }
+
+    #[test]
+    fn decode_element_limit() {
+        let limits = DecodeLimits {
+            max_elements: 1,
+            ..DecodeLimits::unlimited()
+        };
+
+        let bytes = vec![0x30, 0x02, 0x10, 0x01, 0x10, 0x02];
+        let err = decode_with_limits(&bytes, limits).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::ElementLimitExceeded);
+        assert_eq!(err.offset, 1);
+    }
+
+    #[test]
+    fn decode_allocation_limit() {
+        let limits = DecodeLimits {
+            max_total_bytes: 3,
+            ..DecodeLimits::unlimited()
+        };
+
+        // ["ab", "cd"] under a 3 byte budget
+        let bytes = vec![0x30, 0x02, 0x20, 0x02, b'a', b'b', 0x20, 0x02, b'c', b'd'];
+        let err = decode_with_limits(&bytes, limits).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::AllocationLimitExceeded);
+        assert_eq!(err.offset, 1);
+
+        assert_eq!(
+            decode_with_limits(&[0x21, 0x03, 1, 2, 3], limits),
+            Ok(Value::Bytes(vec![1, 2, 3]))
+        );
+    }
+
+    // borrowed decoding
+    #[test]
+    fn decode_ref_borrows_from_input() {
+        // { "blob": 0xaabb, "name": "hi" }
+        let bytes = vec![
+            0x40, 0x02, // map with 2 entries
+            0x20, 0x04, b'b', b'l', b'o', b'b', 0x21, 0x02, 0xaa, 0xbb, // blob
+            0x20, 0x04, b'n', b'a', b'm', b'e', 0x20, 0x02, b'h', b'i', // name
+        ];
+
+        let value = decode_ref(&bytes).unwrap();
+        let ValueRef::Map(map) = &value else {
+            panic!("expected map, got {:?}", value);
+        };
+
+        let ValueRef::Bytes(blob) = map["blob"] else {
+            panic!("expected bytes");
+        };
+        assert_eq!(blob, &[0xaa, 0xbb]);
+        assert!(std::ptr::eq(blob.as_ptr(), bytes[10..].as_ptr()));
+
+        assert_eq!(map["name"], ValueRef::String("hi"));
+        assert_eq!(value.to_owned(), decode(&bytes).unwrap());
+    }
+
+    #[test]
+    fn decode_ref_errors_match_decode() {
+        for bytes in [
+            vec![0xFF],
+            vec![0x00, 0x00],
+            vec![0x20, 0x05, b'h'],
+            vec![0x20, 0x02, 0xff, 0xff],
+        ] {
+            assert_eq!(decode_ref(&bytes).unwrap_err(), decode(&bytes).unwrap_err());
+        }
+    }
+
+    #[test]
+    fn decode_ref_canonical() {
+        let options = DecodeOptions {
+            canonical: true,
+            ..DecodeOptions::default()
+        };
+
+        // { 'b': 1, 'a': 2 }
+        let bytes = vec![
+            0x40, 0x02, 0x20, 0x01, b'b', 0x10, 0x01, 0x20, 0x01, b'a', 0x10, 0x02,
+        ];
+
+        let err = decode_ref_with_options(&bytes, options).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnsortedMapKeys);
+        assert_eq!(err.offset, 7);
+    }
 }
@@ -0,0 +1,155 @@
+use unicode_width::UnicodeWidthChar;
+
+use crate::error::{Expected, ParseError, ParseErrorKind};
+use crate::lexer::TokenKind;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders parse errors against the source they came from.
+///
+/// ```text
+/// error: expected ':' after key `retries`, found integer 3
+///  --> config.st:2:11
+///   |
+/// 2 |   retries 3
+///   |           ^
+///   = hint: map entries are written `key: value` or `key { ... }`
+/// ```
+pub struct Diagnostic<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Self {
+            file_name,
+            source,
+            color: false,
+        }
+    }
+
+    /// Wrap the output in ANSI colour codes. Off by default.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The rendered diagnostic, ending in a newline.
+    pub fn render(&self, err: &ParseError) -> String {
+        let span = err.span;
+        // spans from another source may not land on a character
+        let offset = self.source.floor_char_boundary(span.offset);
+
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[offset..]
+            .find('\n')
+            .map_or(self.source.len(), |i| offset + i);
+        let line = self.source[line_start..line_end].trim_end_matches('\r');
+
+        // keep tabs and pad wide characters so the caret lines up with the
+        // source line as a terminal shows it
+        let mut padding = String::new();
+        for ch in self.source[line_start..offset].chars() {
+            match ch {
+                '\t' => padding.push('\t'),
+                _ => padding.push_str(&" ".repeat(display_width(ch))),
+            }
+        }
+
+        let width = match &err.kind {
+            ParseErrorKind::InvalidEscape(escape) => escape.chars().map(display_width).sum(),
+            _ => 1,
+        };
+
+        let line_number = span.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = self.paint(BLUE, "|");
+
+        let mut out = String::new();
+
+        out.push_str(&format!(
+            "{}{}\n",
+            self.paint(RED, "error"),
+            self.paint(BOLD, &format!(": {}", err.kind))
+        ));
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            self.paint(BLUE, "-->"),
+            self.file_name,
+            span.line,
+            span.column
+        ));
+        out.push_str(&format!("{} {}\n", gutter, bar));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            self.paint(BLUE, &line_number),
+            bar,
+            line
+        ));
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            bar,
+            padding,
+            self.paint(RED, &"^".repeat(width))
+        ));
+
+        if let Some(hint) = hint(&err.kind) {
+            out.push_str(&format!(
+                "{} {} hint: {}\n",
+                gutter,
+                self.paint(BLUE, "="),
+                hint
+            ));
+        }
+
+        out
+    }
+}
+
+fn display_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+fn hint(kind: &ParseErrorKind) -> Option<&'static str> {
+    match kind {
+        ParseErrorKind::UnexpectedToken {
+            expected: Expected::Colon { .. },
+            ..
+        } => Some("map entries are written `key: value` or `key { ... }`"),
+        ParseErrorKind::UnexpectedToken {
+            expected: Expected::MapKey,
+            ..
+        } => Some("keys that are not identifiers must be quoted, e.g. \"content-type\""),
+        ParseErrorKind::UnexpectedToken {
+            found: TokenKind::EOF,
+            ..
+        } => Some("the input ended early; check for a missing ']' or '}'"),
+        ParseErrorKind::UnexpectedToken { .. } => None,
+        ParseErrorKind::InvalidCharacter(_) => None,
+        ParseErrorKind::UnterminatedString => {
+            Some("strings must end on the line they start; write line breaks as \\n")
+        }
+        ParseErrorKind::InvalidEscape(_) => {
+            Some("supported escapes are \\\" \\\\ \\n \\r \\t \\uXXXX and \\u{X...}")
+        }
+        ParseErrorKind::MalformedBytesLiteral => {
+            Some("bytes are hex with an even number of digits (0xdead) or base64 (b64\"3q0=\")")
+        }
+        ParseErrorKind::IntegerOutOfRange => Some("integers must fit in a signed 64-bit value"),
+    }
+}
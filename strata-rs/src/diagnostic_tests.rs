@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+    use crate::error::{ParseError, ParseErrorKind, Span};
+    use crate::parser::parse;
+
+    fn render(source: &str) -> String {
+        let err = parse(source).unwrap_err();
+        Diagnostic::new("config.st", source).render(&err)
+    }
+
+    #[test]
+    fn renders_line_caret_and_hint() {
+        let source = "{\n  retries 3\n}\n";
+
+        assert_eq!(
+            render(source),
+            "error: expected ':' after key `retries`, found integer 3\n \
+             --> config.st:2:11\n  \
+             |\n\
+             2 |   retries 3\n  \
+             |           ^\n  \
+             = hint: map entries are written `key: value` or `key { ... }`\n"
+        );
+    }
+
+    #[test]
+    fn underlines_whole_escape() {
+        let rendered = render("a { s: \"x\\q\" }");
+
+        assert!(rendered.starts_with("error: invalid escape `\\q` in string\n"));
+        assert!(rendered.contains("\n1 | a { s: \"x\\q\" }\n  |          ^^\n"));
+        assert!(rendered.contains("= hint: supported escapes"));
+    }
+
+    #[test]
+    fn caret_follows_tabs_and_wide_gutter() {
+        let source = format!("{}{{\n\tbad: @\n}}", "\n".repeat(9));
+        let rendered = render(&source);
+
+        assert!(rendered.contains(" --> config.st:11:7\n"));
+        assert!(rendered.contains("\n11 | \tbad: @\n   | \t     ^\n"));
+        assert!(!rendered.contains("hint"));
+    }
+
+    #[test]
+    fn caret_counts_wide_characters_twice() {
+        let rendered = render("{ \"日本\": 1 2 }");
+
+        assert!(rendered.contains("\n1 | { \"日本\": 1 2 }\n  |             ^\n"));
+    }
+
+    #[test]
+    fn offset_inside_a_character_is_clamped() {
+        let source = "{ a: \"é\" }";
+        let err = ParseError {
+            kind: ParseErrorKind::IntegerOutOfRange,
+            span: Span {
+                offset: 7,
+                line: 1,
+                column: 8,
+            },
+        };
+
+        let rendered = Diagnostic::new("config.st", source).render(&err);
+        assert!(rendered.contains("\n1 | { a: \"é\" }\n  |       ^\n"));
+    }
+
+    #[test]
+    fn error_at_end_of_input() {
+        let rendered = render("[1, 2");
+
+        assert!(rendered.contains("found end of input"));
+        assert!(rendered.contains("\n1 | [1, 2\n  |      ^\n"));
+        assert!(rendered.contains("missing ']' or '}'"));
+    }
+
+    #[test]
+    fn color_wraps_output_in_ansi_codes() {
+        let source = "[1 2]";
+        let err = parse(source).unwrap_err();
+
+        let plain = Diagnostic::new("a.st", source).render(&err);
+        let colored = Diagnostic::new("a.st", source).color(true).render(&err);
+
+        assert!(!plain.contains('\x1b'));
+        assert!(colored.starts_with("\x1b[1;31merror\x1b[0m"));
+    }
+}
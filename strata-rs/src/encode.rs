@@ -1,4 +1,6 @@
-use crate::error::EncodeError;
+use std::io::Write;
+
+use crate::error::{EncodeError, StrataError};
 use crate::value::Value;
 
 pub fn encode_uleb128(mut value: u64, out: &mut Vec<u8>) {
@@ -31,13 +33,50 @@ pub fn encode_sleb128(mut value: i64, out: &mut Vec<u8>) {
     }
 }
 
+/// Number of bytes `encode_uleb128` writes for `value`.
+pub fn uleb128_len(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    bits.div_ceil(7).max(1)
+}
+
+/// Number of bytes `encode_sleb128` writes for `value`.
+pub fn sleb128_len(value: i64) -> usize {
+    let magnitude = if value < 0 { !value } else { value };
+    // significant bits plus the sign bit
+    let bits = 64 - magnitude.leading_zeros() as usize + 1;
+    bits.div_ceil(7)
+}
+
+/// Exact length of `encode(value)` in bytes, computed without allocating.
+pub fn encoded_len(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) => 1,
+        Value::Int(number) => 1 + sleb128_len(*number),
+        Value::String(string) => 1 + uleb128_len(string.len() as u64) + string.len(),
+        Value::Bytes(bytes) => 1 + uleb128_len(bytes.len() as u64) + bytes.len(),
+        Value::List(items) => {
+            1 + uleb128_len(items.len() as u64) + items.iter().map(encoded_len).sum::<usize>()
+        }
+        Value::Map(map) => {
+            1 + uleb128_len(map.len() as u64)
+                + map
+                    .iter()
+                    .map(|(key, value)| {
+                        1 + uleb128_len(key.len() as u64) + key.len() + encoded_len(value)
+                    })
+                    .sum::<usize>()
+        }
+    }
+}
+
 pub fn encode(value: &Value) -> Result<Vec<u8>, EncodeError> {
     let mut out = Vec::new();
     encode_into(value, &mut out)?;
     Ok(out)
 }
 
-fn encode_into(value: &Value, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+/// Append the canonical encoding of `value` to `out`, reusing its capacity.
+pub fn encode_into(value: &Value, out: &mut Vec<u8>) -> Result<(), EncodeError> {
     match value {
         Value::Null => {
             out.push(0x00);
@@ -106,3 +145,71 @@ fn encode_into(value: &Value, out: &mut Vec<u8>) -> Result<(), EncodeError> {
 
     Ok(())
 }
+
+/// Write the canonical encoding of `value` to `out` without building the
+/// whole payload in memory first.
+///
+/// Many small writes are issued; wrap unbuffered sinks in a `BufWriter`.
+pub fn encode_to_writer<W: Write>(value: &Value, out: &mut W) -> Result<(), StrataError> {
+    let mut header = Vec::with_capacity(16);
+    write_value(value, out, &mut header)
+}
+
+// `header` is scratch space for tags and varints
+fn write_value<W: Write>(
+    value: &Value,
+    out: &mut W,
+    header: &mut Vec<u8>,
+) -> Result<(), StrataError> {
+    header.clear();
+
+    match value {
+        Value::String(string) => {
+            header.push(0x20);
+            encode_uleb128(string.len() as u64, header);
+            out.write_all(header)?;
+            out.write_all(string.as_bytes())?;
+        }
+
+        Value::Bytes(bytes) => {
+            header.push(0x21);
+            encode_uleb128(bytes.len() as u64, header);
+            out.write_all(header)?;
+            out.write_all(bytes)?;
+        }
+
+        Value::List(items) => {
+            header.push(0x30);
+            encode_uleb128(items.len() as u64, header);
+            out.write_all(header)?;
+
+            for item in items {
+                write_value(item, out, header)?;
+            }
+        }
+
+        Value::Map(map) => {
+            header.push(0x40);
+            encode_uleb128(map.len() as u64, header);
+            out.write_all(header)?;
+
+            for (key, value) in map {
+                header.clear();
+                header.push(0x20);
+                encode_uleb128(key.len() as u64, header);
+                out.write_all(header)?;
+                out.write_all(key.as_bytes())?;
+
+                write_value(value, out, header)?;
+            }
+        }
+
+        // scalars are a handful of bytes
+        scalar => {
+            encode_into(scalar, header)?;
+            out.write_all(header)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,14 @@
 #[cfg(test)]
 mod tests {
-    use crate::encode::{encode, encode_sleb128, encode_uleb128};
+    use proptest::prelude::*;
+
+    use crate::encode::{
+        encode, encode_into, encode_sleb128, encode_to_writer, encode_uleb128, encoded_len,
+        sleb128_len, uleb128_len,
+    };
+    use crate::error::StrataError;
     use crate::framing::encode_framed;
+    use crate::test_strategies::arb_value;
     use crate::value::Value;
 
     #[test]
@@ -135,4 +142,87 @@ mod tests {
             vec![b'S', b'T', b'R', b'A', b'T', b'A', b'1', 0x01, 0x10, 0x01]
         );
     }
+
+    // sinks
+    fn sample() -> Value {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("b".into(), Value::Bytes(vec![0xDE, 0xAD]));
+        map.insert("a".into(), Value::List(vec![Value::Int(-1), Value::Null]));
+        map.insert("s".into(), Value::String("hi".into()));
+        Value::Map(map)
+    }
+
+    #[test]
+    fn encode_into_appends() {
+        let mut out = vec![0xAA];
+        encode_into(&Value::Int(1), &mut out).unwrap();
+        encode_into(&Value::Bool(true), &mut out).unwrap();
+
+        assert_eq!(out, vec![0xAA, 0x10, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn encode_to_writer_matches_encode() {
+        let value = sample();
+
+        let mut out = Vec::new();
+        encode_to_writer(&value, &mut out).unwrap();
+
+        assert_eq!(out, encode(&value).unwrap());
+    }
+
+    #[test]
+    fn encode_to_writer_reports_io_errors() {
+        let mut out = [0u8; 4];
+        let mut sink = &mut out[..];
+
+        let err = encode_to_writer(&sample(), &mut sink).unwrap_err();
+        assert!(matches!(err, StrataError::Io(_)));
+    }
+
+    // size computation
+    #[test]
+    fn varint_len_boundaries() {
+        for value in [0, 1, 127, 128, 16383, 16384, u32::MAX as u64, u64::MAX] {
+            let mut out = Vec::new();
+            encode_uleb128(value, &mut out);
+            assert_eq!(uleb128_len(value), out.len(), "uleb128 {}", value);
+        }
+
+        for value in [
+            0,
+            63,
+            64,
+            -64,
+            -65,
+            8191,
+            8192,
+            -8192,
+            -8193,
+            i64::MAX,
+            i64::MIN,
+        ] {
+            let mut out = Vec::new();
+            encode_sleb128(value, &mut out);
+            assert_eq!(sleb128_len(value), out.len(), "sleb128 {}", value);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn encoded_len_matches_encode(value in arb_value()) {
+            prop_assert_eq!(encoded_len(&value), encode(&value).unwrap().len());
+        }
+
+        #[test]
+        fn varint_len_matches_encoding(u in any::<u64>(), i in any::<i64>()) {
+            let mut out = Vec::new();
+            encode_uleb128(u, &mut out);
+            prop_assert_eq!(uleb128_len(u), out.len());
+
+            out.clear();
+            encode_sleb128(i, &mut out);
+            prop_assert_eq!(sleb128_len(i), out.len());
+        }
+    }
 }
@@ -1,28 +1,62 @@
+use std::fmt;
 use std::io;
 
-#[derive(Debug)]
+use crate::lexer::TokenKind;
+
+#[derive(Debug, thiserror::Error)]
 pub enum StrataError {
+    #[error("parse error: {0}")]
     Parse(ParseError),
+    #[error("encode error: {0}")]
     Encode(EncodeError),
+    #[error("decode error: {0}")]
     Decode(DecodeError),
-    Io(io::Error),
+    #[error("framing error: {0}")]
+    Frame(FrameError),
+    #[error("I/O error: {0}")]
+    Io(#[source] io::Error),
+    #[error("internal error: {0}")]
     Internal(&'static str),
 }
 
 // Decode errors
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} at offset {offset}")]
 pub struct DecodeError {
     pub kind: DecodeErrorKind,
     pub offset: usize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum DecodeErrorKind {
+    #[error("invalid tag 0x{0:02x}")]
     InvalidTag(u8),
+    #[error("unexpected end of input")]
     UnexpectedEOF,
+    #[error("invalid varint")]
     InvalidVarint,
+    #[error("invalid UTF-8 in string")]
     InvalidUtf8,
+    #[error("trailing bytes after value")]
     TrailingBytes,
+
+    // canonical mode only
+    #[error("non-canonical varint")]
+    NonCanonicalVarint,
+    #[error("map keys not in canonical order")]
+    UnsortedMapKeys,
+    #[error("duplicate map key")]
+    DuplicateMapKey,
+
+    // resource limits
+    #[error("depth limit exceeded")]
+    DepthLimitExceeded,
+    #[error("length limit exceeded")]
+    LengthLimitExceeded,
+    #[error("element limit exceeded")]
+    ElementLimitExceeded,
+    #[error("allocation limit exceeded")]
+    AllocationLimitExceeded,
 }
 
 // Parse errors
@@ -33,37 +67,331 @@ pub struct Span {
     pub column: usize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Source range from `start` up to `end`, which is the position just past
+/// the last character.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SpanRange {
+    pub start: Span,
+    pub end: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} at line {}, column {}", span.line, span.column)]
 pub struct ParseError {
     pub kind: ParseErrorKind,
     pub span: Span,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum ParseErrorKind {
     // syntax
+    /// Displays as e.g. "expected ':' after key `retries`, found integer 3".
+    #[error("expected {expected}, found {found}")]
     UnexpectedToken {
-        expected: &'static str,
-        found: &'static str,
+        expected: Expected,
+        found: TokenKind,
     },
+    #[error("invalid character {0:?}")]
+    InvalidCharacter(char),
+    #[error("unterminated string")]
+    UnterminatedString,
+    /// The escape as written, e.g. `\q` or `\u{110000}`.
+    #[error("invalid escape `{0}` in string")]
+    InvalidEscape(String),
+    #[error("malformed bytes literal")]
     MalformedBytesLiteral,
 
     // semantic
+    #[error("integer out of range")]
     IntegerOutOfRange,
 }
 
+/// What the parser was looking for when it met an unexpected token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    Value,
+    EndOfInput,
+    /// One particular token, like the `[` that opens a list.
+    Token(TokenKind),
+    /// A key, or the `}` that closes the map.
+    MapKey,
+    /// The `:` between a key and its value.
+    Colon {
+        key: String,
+    },
+    /// The `{` of a `key { ... }` shorthand.
+    ShorthandBrace {
+        key: String,
+    },
+    /// A `,` or `]` after a list element.
+    ListSeparator,
+    /// A `,` or `}` after a map entry.
+    EntrySeparator,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Value => f.write_str("value"),
+            Expected::EndOfInput => f.write_str("end of input"),
+            Expected::Token(kind) => write!(f, "{}", kind),
+            Expected::MapKey => f.write_str("map key or '}'"),
+            Expected::Colon { key } => write!(f, "':' after key `{}`", key),
+            Expected::ShorthandBrace { key } => write!(f, "'{{' after `{}`", key),
+            Expected::ListSeparator => f.write_str("',' or ']' after list element"),
+            Expected::EntrySeparator => f.write_str("',' or '}' after map entry"),
+        }
+    }
+}
+
 // Encode errors
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum EncodeError {
+    #[error("duplicate map key")]
     DuplicateKey,
     // InvalidUtf8 is unreachable in Rust because String
     // is UTF-8 by construction.
     // It exists for spec completeness and non-Rust
     // implementations.
+    #[error("invalid UTF-8 in string")]
     InvalidUtf8,
+    #[error("invalid integer")]
     InvalidInteger,
 }
 
+// Framing errors
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    // magic header framing
+    #[error("truncated frame header")]
+    TruncatedHeader,
+    #[error("bad frame magic")]
+    BadMagic,
+    #[error("unsupported frame version {0}")]
+    UnsupportedVersion(u8),
+
+    // length-delimited framing
+    #[error("empty frame")]
+    EmptyFrame,
+    #[error("frame of {len} bytes exceeds maximum of {max}")]
+    FrameTooLarge { len: usize, max: usize },
+    #[error("truncated frame")]
+    TruncatedFrame,
+
+    // payload failed to decode
+    #[error("invalid payload: {0}")]
+    Decode(DecodeError),
+}
+
+// Conversion errors
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{kind}{}", at_path(.path))]
+pub struct ConvertError {
+    pub kind: ConvertErrorKind,
+    /// Where in the value the error is, e.g. `service.ports[1]`. Empty
+    /// for the value itself.
+    pub path: String,
+}
+
+fn at_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at `{}`", path)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ConvertErrorKind {
+    /// Both sides name a kind of value, e.g. "int" and "string".
+    #[error("expected {expected}, found {found}")]
+    InvalidType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("expected {expected} elements, found {found}")]
+    InvalidLength { expected: usize, found: usize },
+    #[error("missing field")]
+    MissingField,
+    /// The integer and the Rust type it did not fit, e.g. "u8".
+    #[error("integer {0} out of range for {1}")]
+    IntegerOutOfRange(i64, &'static str),
+    #[error("unknown variant `{0}`")]
+    UnknownVariant(String),
+}
+
+// Serde errors
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SerdeError {
+    /// Raised by a `Serialize` or `Deserialize` impl, e.g. "missing field
+    /// `port`" or "invalid type: string \"x\", expected u16".
+    #[error("{0}")]
+    Message(String),
+    /// The integer as written by the Rust value, e.g. "18446744073709551615".
+    #[error("integer {0} does not fit in i64")]
+    IntegerOutOfRange(String),
+    /// Strata has no floats.
+    #[error("{0} is not a Strata type")]
+    UnsupportedType(&'static str),
+    #[error("map keys must be strings")]
+    KeyMustBeString,
+    #[error("encode error: {0}")]
+    Encode(EncodeError),
+    #[error("decode error: {0}")]
+    Decode(DecodeError),
+}
+
+// Error codes are stable across releases and safe to match on in tooling.
+// Messages may change; codes may not.
+
+impl StrataError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StrataError::Parse(err) => err.code(),
+            StrataError::Encode(err) => err.code(),
+            StrataError::Decode(err) => err.code(),
+            StrataError::Frame(err) => err.code(),
+            StrataError::Io(_) => "io",
+            StrataError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl DecodeError {
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl DecodeErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DecodeErrorKind::InvalidTag(_) => "decode.invalid_tag",
+            DecodeErrorKind::UnexpectedEOF => "decode.unexpected_eof",
+            DecodeErrorKind::InvalidVarint => "decode.invalid_varint",
+            DecodeErrorKind::InvalidUtf8 => "decode.invalid_utf8",
+            DecodeErrorKind::TrailingBytes => "decode.trailing_bytes",
+            DecodeErrorKind::NonCanonicalVarint => "decode.non_canonical_varint",
+            DecodeErrorKind::UnsortedMapKeys => "decode.unsorted_map_keys",
+            DecodeErrorKind::DuplicateMapKey => "decode.duplicate_map_key",
+            DecodeErrorKind::DepthLimitExceeded => "decode.depth_limit_exceeded",
+            DecodeErrorKind::LengthLimitExceeded => "decode.length_limit_exceeded",
+            DecodeErrorKind::ElementLimitExceeded => "decode.element_limit_exceeded",
+            DecodeErrorKind::AllocationLimitExceeded => "decode.allocation_limit_exceeded",
+        }
+    }
+}
+
+impl ParseError {
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl ParseErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::UnexpectedToken { .. } => "parse.unexpected_token",
+            ParseErrorKind::InvalidCharacter(_) => "parse.invalid_character",
+            ParseErrorKind::UnterminatedString => "parse.unterminated_string",
+            ParseErrorKind::InvalidEscape(_) => "parse.invalid_escape",
+            ParseErrorKind::MalformedBytesLiteral => "parse.malformed_bytes_literal",
+            ParseErrorKind::IntegerOutOfRange => "parse.integer_out_of_range",
+        }
+    }
+}
+
+impl EncodeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EncodeError::DuplicateKey => "encode.duplicate_key",
+            EncodeError::InvalidUtf8 => "encode.invalid_utf8",
+            EncodeError::InvalidInteger => "encode.invalid_integer",
+        }
+    }
+}
+
+impl FrameError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FrameError::TruncatedHeader => "frame.truncated_header",
+            FrameError::BadMagic => "frame.bad_magic",
+            FrameError::UnsupportedVersion(_) => "frame.unsupported_version",
+            FrameError::EmptyFrame => "frame.empty_frame",
+            FrameError::FrameTooLarge { .. } => "frame.too_large",
+            FrameError::TruncatedFrame => "frame.truncated_frame",
+            FrameError::Decode(err) => err.code(),
+        }
+    }
+}
+
+impl ConvertError {
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl ConvertErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConvertErrorKind::InvalidType { .. } => "convert.invalid_type",
+            ConvertErrorKind::InvalidLength { .. } => "convert.invalid_length",
+            ConvertErrorKind::MissingField => "convert.missing_field",
+            ConvertErrorKind::IntegerOutOfRange(..) => "convert.integer_out_of_range",
+            ConvertErrorKind::UnknownVariant(_) => "convert.unknown_variant",
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl SerdeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SerdeError::Message(_) => "serde.message",
+            SerdeError::IntegerOutOfRange(_) => "serde.integer_out_of_range",
+            SerdeError::UnsupportedType(_) => "serde.unsupported_type",
+            SerdeError::KeyMustBeString => "serde.key_must_be_string",
+            SerdeError::Encode(err) => err.code(),
+            SerdeError::Decode(err) => err.code(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<EncodeError> for SerdeError {
+    fn from(err: EncodeError) -> Self {
+        SerdeError::Encode(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<DecodeError> for SerdeError {
+    fn from(err: DecodeError) -> Self {
+        SerdeError::Decode(err)
+    }
+}
+
+impl From<DecodeError> for FrameError {
+    fn from(err: DecodeError) -> Self {
+        FrameError::Decode(err)
+    }
+}
+
 impl From<ParseError> for StrataError {
     fn from(err: ParseError) -> Self {
         StrataError::Parse(err)
@@ -82,6 +410,12 @@ impl From<DecodeError> for StrataError {
     }
 }
 
+impl From<FrameError> for StrataError {
+    fn from(err: FrameError) -> Self {
+        StrataError::Frame(err)
+    }
+}
+
 impl From<io::Error> for StrataError {
     fn from(err: io::Error) -> Self {
         StrataError::Io(err)
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::error::Error;
+    use std::io;
+
+    use crate::decode::decode;
+    use crate::error::{
+        ConvertErrorKind, DecodeError, DecodeErrorKind, EncodeError, Expected, FrameError,
+        ParseErrorKind, StrataError,
+    };
+    use crate::lexer::TokenKind;
+    use crate::parser::parse;
+
+    #[test]
+    fn decode_error_display() {
+        let err = decode(&[0x30, 0x01, 0x99]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid tag 0x99 at offset 3");
+
+        let err = StrataError::from(err);
+        assert_eq!(
+            err.to_string(),
+            "decode error: invalid tag 0x99 at offset 3"
+        );
+        assert_eq!(err.code(), "decode.invalid_tag");
+    }
+
+    #[test]
+    fn parse_error_display() {
+        let err = StrataError::from(parse("[1,\n 2 3]").unwrap_err());
+
+        assert_eq!(
+            err.to_string(),
+            "parse error: expected ',' or ']' after list element, found integer 3 at line 2, column 4"
+        );
+        assert_eq!(err.code(), "parse.unexpected_token");
+    }
+
+    #[test]
+    fn frame_and_encode_error_display() {
+        let err = FrameError::FrameTooLarge { len: 20, max: 16 };
+        assert_eq!(err.to_string(), "frame of 20 bytes exceeds maximum of 16");
+
+        let err = FrameError::Decode(DecodeError {
+            kind: DecodeErrorKind::TrailingBytes,
+            offset: 4,
+        });
+        assert_eq!(
+            err.to_string(),
+            "invalid payload: trailing bytes after value at offset 4"
+        );
+        assert_eq!(err.code(), "decode.trailing_bytes");
+
+        assert_eq!(EncodeError::DuplicateKey.to_string(), "duplicate map key");
+    }
+
+    #[test]
+    fn io_error_is_chained_as_source() {
+        let err = StrataError::from(io::Error::new(io::ErrorKind::NotFound, "missing.st"));
+
+        assert_eq!(err.to_string(), "I/O error: missing.st");
+        assert_eq!(err.code(), "io");
+
+        let source = err.source().expect("io error source");
+        assert_eq!(source.to_string(), "missing.st");
+
+        let parse_err = StrataError::from(parse("@").unwrap_err());
+        assert!(parse_err.source().is_none());
+    }
+
+    #[test]
+    fn errors_convert_into_boxed_error() {
+        fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
+            decode(&[0x10])?;
+            Ok(())
+        }
+
+        let err = run().unwrap_err();
+        assert!(err.downcast_ref::<DecodeError>().is_some());
+    }
+
+    #[test]
+    fn error_codes_are_unique() {
+        let codes = [
+            DecodeErrorKind::InvalidTag(0).code(),
+            DecodeErrorKind::UnexpectedEOF.code(),
+            DecodeErrorKind::InvalidVarint.code(),
+            DecodeErrorKind::InvalidUtf8.code(),
+            DecodeErrorKind::TrailingBytes.code(),
+            DecodeErrorKind::NonCanonicalVarint.code(),
+            DecodeErrorKind::UnsortedMapKeys.code(),
+            DecodeErrorKind::DuplicateMapKey.code(),
+            DecodeErrorKind::DepthLimitExceeded.code(),
+            DecodeErrorKind::LengthLimitExceeded.code(),
+            DecodeErrorKind::ElementLimitExceeded.code(),
+            DecodeErrorKind::AllocationLimitExceeded.code(),
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::Value,
+                found: TokenKind::EOF,
+            }
+            .code(),
+            ParseErrorKind::InvalidCharacter('@').code(),
+            ParseErrorKind::UnterminatedString.code(),
+            ParseErrorKind::InvalidEscape(String::new()).code(),
+            ParseErrorKind::MalformedBytesLiteral.code(),
+            ParseErrorKind::IntegerOutOfRange.code(),
+            EncodeError::DuplicateKey.code(),
+            EncodeError::InvalidUtf8.code(),
+            EncodeError::InvalidInteger.code(),
+            FrameError::TruncatedHeader.code(),
+            FrameError::BadMagic.code(),
+            FrameError::UnsupportedVersion(2).code(),
+            FrameError::EmptyFrame.code(),
+            FrameError::FrameTooLarge { len: 0, max: 0 }.code(),
+            FrameError::TruncatedFrame.code(),
+            ConvertErrorKind::InvalidType {
+                expected: "",
+                found: "",
+            }
+            .code(),
+            ConvertErrorKind::InvalidLength {
+                expected: 0,
+                found: 0,
+            }
+            .code(),
+            ConvertErrorKind::MissingField.code(),
+            ConvertErrorKind::IntegerOutOfRange(0, "u8").code(),
+            ConvertErrorKind::UnknownVariant(String::new()).code(),
+            StrataError::Io(io::Error::other("x")).code(),
+            StrataError::Internal("x").code(),
+        ];
+
+        let unique: HashSet<_> = codes.iter().collect();
+        assert_eq!(unique.len(), codes.len());
+    }
+}
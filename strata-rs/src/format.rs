@@ -0,0 +1,514 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cst::{Document, Entry, Item, List, Map, Node, SyntaxToken};
+use crate::lexer::TokenKind;
+use crate::value::Value;
+
+const INDENT: &str = "  ";
+
+/// Render a value as Strata Text (`.st`).
+///
+/// The output is fully determined by the value: maps list their entries
+/// in canonical key order, one per line, and nested maps use the
+/// `key { ... }` shorthand. Parsing the output yields the same value, so
+/// formatting is idempotent.
+pub fn format_value(value: &Value) -> String {
+    let root = match value {
+        // a single top-level map entry is written as `key { ... }`
+        Value::Map(map) if map.len() == 1 => {
+            let (key, inner) = map.iter().next().unwrap();
+
+            if matches!(inner, Value::Map(_)) && is_identifier(key) {
+                Node::Map(Map {
+                    open: None,
+                    entries: vec![Entry {
+                        key: key_token(key, String::new()),
+                        colon: None,
+                        value: format_node(inner, 0, " ".to_string()),
+                        comma: None,
+                    }],
+                    close: None,
+                })
+            } else {
+                format_node(value, 0, String::new())
+            }
+        }
+
+        _ => format_node(value, 0, String::new()),
+    };
+
+    let document = Document {
+        root,
+        eof: SyntaxToken::new(TokenKind::EOF, "", "\n"),
+    };
+    document.to_string()
+}
+
+/// Render a parsed document as Strata Text, keeping its comments.
+///
+/// The layout is the one [`format_value`] gives the document's value. A
+/// comment on a line of its own stays above the entry or element after
+/// it, and one at the end of a line stays at the end of its entry or
+/// element, so comments move with their entries when keys are sorted.
+/// Comments before a closing bracket or the end of input stay there, and
+/// those of an entry a later duplicate key overrides go to the entry that
+/// wins.
+pub fn format_document(document: &Document) -> String {
+    let mut writer = Writer {
+        comments: Comments::new(document),
+        out: String::new(),
+        pending: Vec::new(),
+    };
+
+    writer.write_root(&document.root);
+    writer.comment_lines(writer.comments.above(&document.eof), 0);
+    writer.line(0);
+    writer.out
+}
+
+/// The syntax tree of `value` as written `depth` levels deep inside a
+/// document, without the root shorthand.
+pub(crate) fn format_node(value: &Value, depth: usize, leading_trivia: String) -> Node {
+    let scalar =
+        |kind: TokenKind, text: &str| Node::Scalar(SyntaxToken::new(kind, text, &leading_trivia));
+
+    match value {
+        Value::Null => scalar(TokenKind::Null, "null"),
+        Value::Bool(true) => scalar(TokenKind::True, "true"),
+        Value::Bool(false) => scalar(TokenKind::False, "false"),
+        Value::Int(number) => scalar(TokenKind::Int(*number), &number.to_string()),
+
+        Value::String(string) => {
+            let mut text = String::new();
+            write_string(string, &mut text);
+            scalar(TokenKind::String(string.clone()), &text)
+        }
+
+        Value::Bytes(bytes) => {
+            let mut text = String::new();
+            write_bytes(bytes, &mut text);
+            scalar(TokenKind::Bytes(bytes.clone()), &text)
+        }
+
+        Value::List(items) => {
+            // lists of scalars stay on one line
+            let inline = items.iter().all(is_scalar);
+
+            let items: Vec<Item> = items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let trivia = match (inline, i) {
+                        (true, 0) => String::new(),
+                        (true, _) => " ".to_string(),
+                        (false, _) => line_break(depth + 1),
+                    };
+
+                    Item {
+                        value: format_node(item, depth + 1, trivia),
+                        comma: (i + 1 < items.len())
+                            .then(|| SyntaxToken::new(TokenKind::Comma, ",", "")),
+                    }
+                })
+                .collect();
+
+            let close = if inline {
+                String::new()
+            } else {
+                line_break(depth)
+            };
+
+            Node::List(List {
+                open: SyntaxToken::new(TokenKind::LBracket, "[", &leading_trivia),
+                items,
+                close: SyntaxToken::new(TokenKind::RBracket, "]", &close),
+            })
+        }
+
+        Value::Map(map) => {
+            let entries: Vec<Entry> = map
+                .iter()
+                .map(|(key, value)| {
+                    let colon = (!matches!(value, Value::Map(_)))
+                        .then(|| SyntaxToken::new(TokenKind::Colon, ":", ""));
+
+                    Entry {
+                        key: key_token(key, line_break(depth + 1)),
+                        colon,
+                        value: format_node(value, depth + 1, " ".to_string()),
+                        comma: None,
+                    }
+                })
+                .collect();
+
+            let close = if entries.is_empty() {
+                String::new()
+            } else {
+                line_break(depth)
+            };
+
+            Node::Map(Map {
+                open: Some(SyntaxToken::new(TokenKind::LBrace, "{", &leading_trivia)),
+                entries,
+                close: Some(SyntaxToken::new(TokenKind::RBrace, "}", &close)),
+            })
+        }
+    }
+}
+
+/// A newline and the indentation for `depth`.
+pub(crate) fn line_break(depth: usize) -> String {
+    let mut out = "\n".to_string();
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+    out
+}
+
+/// A map key token: an identifier where possible, otherwise a string.
+pub(crate) fn key_token(key: &str, leading_trivia: String) -> SyntaxToken {
+    if is_identifier(key) {
+        return SyntaxToken::new(TokenKind::Ident(key.to_string()), key, &leading_trivia);
+    }
+
+    let mut text = String::new();
+    write_string(key, &mut text);
+    SyntaxToken::new(TokenKind::String(key.to_string()), &text, &leading_trivia)
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::List(_) | Value::Map(_))
+}
+
+/// Keys that lex as a plain identifier token.
+pub(crate) fn is_identifier(key: &str) -> bool {
+    let mut bytes = key.bytes();
+
+    let starts_ok = matches!(bytes.next(), Some(b'a'..=b'z' | b'A'..=b'Z' | b'_'));
+    let rest_ok = bytes.all(|b| matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_'));
+
+    starts_ok && rest_ok && !matches!(key, "null" | "true" | "false")
+}
+
+fn write_string(string: &str, out: &mut String) {
+    out.push('"');
+
+    for ch in string.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // all control characters are in the BMP
+            _ if ch.is_control() => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            _ => out.push(ch),
+        }
+    }
+
+    out.push('"');
+}
+
+fn write_bytes(bytes: &[u8], out: &mut String) {
+    out.push_str("0x");
+    for byte in bytes {
+        out.push_str(&format!("{:02x}", byte));
+    }
+}
+
+// Comments keyed by the token they belong to. Tokens are compared by
+// address, as edited documents have tokens without spans.
+struct Comments<'a> {
+    // on lines of their own in front of the token
+    above: HashMap<*const SyntaxToken, Vec<&'a str>>,
+    // at the end of the token's line
+    trailing: HashMap<*const SyntaxToken, &'a str>,
+}
+
+impl<'a> Comments<'a> {
+    fn new(document: &'a Document) -> Self {
+        let mut comments = Comments {
+            above: HashMap::new(),
+            trailing: HashMap::new(),
+        };
+
+        let mut previous: Option<&'a SyntaxToken> = None;
+        let mut attach = |token: &'a SyntaxToken| {
+            // the first line of trivia ends the previous token's line
+            let (first, rest) = token
+                .leading_trivia
+                .split_once('\n')
+                .unwrap_or((&token.leading_trivia, ""));
+            let first = first.trim();
+            let mut above: Vec<&str> = rest
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+
+            if !first.is_empty() {
+                match previous {
+                    Some(previous) => {
+                        comments.trailing.insert(previous, first);
+                    }
+                    None => above.insert(0, first),
+                }
+            }
+            comments.above.insert(token, above);
+            previous = Some(token);
+        };
+
+        document.root.visit(&mut attach);
+        attach(&document.eof);
+        comments
+    }
+
+    fn above(&self, token: &SyntaxToken) -> Vec<&'a str> {
+        self.above
+            .get(&std::ptr::from_ref(token))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn trailing(&self, token: &SyntaxToken) -> Option<&'a str> {
+        self.trailing.get(&std::ptr::from_ref(token)).copied()
+    }
+
+    // every comment of every token in `node`
+    fn all(&self, node: &Node) -> Vec<&'a str> {
+        let mut all = Vec::new();
+        node.visit(&mut |token| {
+            all.extend(self.above(token));
+            all.extend(self.trailing(token));
+        });
+        all
+    }
+
+    // whether a comment sits anywhere inside `node`, between its first
+    // and last token
+    fn inside(&self, node: &Node) -> bool {
+        let first = node.first_token();
+        let last = node.last_token();
+
+        let mut found = false;
+        node.visit(&mut |token| {
+            found |= !std::ptr::eq(token, first) && !self.above(token).is_empty();
+            found |= !std::ptr::eq(token, last) && self.trailing(token).is_some();
+        });
+        found
+    }
+}
+
+// Writes the document line by line. A trailing comment is held back until
+// its line ends, so it follows whatever was written after its token on
+// that line.
+struct Writer<'a> {
+    comments: Comments<'a>,
+    out: String,
+    pending: Vec<&'a str>,
+}
+
+impl<'a> Writer<'a> {
+    // end the current line, with the comments held for it, and indent the
+    // next one
+    fn line(&mut self, depth: usize) {
+        for (i, comment) in std::mem::take(&mut self.pending).into_iter().enumerate() {
+            match i {
+                0 if !self.out.is_empty() => self.out.push(' '),
+                _ if !self.out.is_empty() => self.out.push_str(&line_break(depth)),
+                _ => {}
+            }
+            self.out.push_str(comment);
+        }
+
+        if !self.out.is_empty() {
+            self.out.push_str(&line_break(depth));
+        }
+    }
+
+    fn comment_lines(&mut self, comments: Vec<&'a str>, depth: usize) {
+        for comment in comments {
+            self.line(depth);
+            self.out.push_str(comment);
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken, text: &str) {
+        self.out.push_str(text);
+        self.pending.extend(self.comments.trailing(token));
+    }
+
+    fn write_root(&mut self, root: &Node) {
+        // a single top-level map entry is written as `key { ... }`
+        if let Node::Map(map) = root {
+            let entries = self.entries(map);
+            if let [(entry, above)] = entries.as_slice()
+                && matches!(entry.value, Node::Map(_))
+                && is_identifier(entry.key())
+            {
+                let mut above_root = Vec::new();
+                if let Some(open) = &map.open {
+                    above_root.extend(self.comments.above(open));
+                    above_root.extend(self.comments.trailing(open));
+                }
+                above_root.extend(above.iter().copied());
+
+                self.write_entry(entry, above_root, 0);
+                if let Some(close) = &map.close {
+                    self.comment_lines(self.comments.above(close), 0);
+                    self.pending.extend(self.comments.trailing(close));
+                }
+                return;
+            }
+        }
+
+        self.comment_lines(self.comments.above(root.first_token()), 0);
+        self.line(0);
+        self.write_node(root, 0);
+    }
+
+    // the entries that make up the map's value, in canonical order, each
+    // with the comments of the duplicates it overrides
+    fn entries<'m>(&self, map: &'m Map) -> Vec<(&'m Entry, Vec<&'a str>)> {
+        let mut entries: BTreeMap<&str, (&Entry, Vec<&str>)> = BTreeMap::new();
+
+        for entry in &map.entries {
+            let mut above = Vec::new();
+            if let Some((overridden, overridden_above)) = entries.remove(entry.key()) {
+                above = overridden_above;
+                above.extend(self.comments.above(&overridden.key));
+                above.extend(self.comments.trailing(&overridden.key));
+                if let Some(colon) = &overridden.colon {
+                    above.extend(self.comments.above(colon));
+                    above.extend(self.comments.trailing(colon));
+                }
+                above.extend(self.comments.all(&overridden.value));
+                if let Some(comma) = &overridden.comma {
+                    above.extend(self.comments.above(comma));
+                    above.extend(self.comments.trailing(comma));
+                }
+            }
+            entries.insert(entry.key(), (entry, above));
+        }
+
+        entries.into_values().collect()
+    }
+
+    // comments in front of a value that is not a shorthand map
+    fn value_above(&self, node: &Node) -> Vec<&'a str> {
+        match node {
+            Node::Map(Map { open: None, .. }) => Vec::new(),
+            node => self.comments.above(node.first_token()),
+        }
+    }
+
+    // `key: value` or `key { ... }`, on the current line, after the
+    // comment lines in `above`
+    fn write_entry(&mut self, entry: &Entry, mut above: Vec<&'a str>, depth: usize) {
+        // comments between the key and its value cannot stay there
+        above.extend(self.comments.above(&entry.key));
+        above.extend(self.comments.trailing(&entry.key));
+        if let Some(colon) = &entry.colon {
+            above.extend(self.comments.above(colon));
+            above.extend(self.comments.trailing(colon));
+        }
+        above.extend(self.value_above(&entry.value));
+
+        self.comment_lines(above, depth);
+        self.line(depth);
+        self.out
+            .push_str(&key_token(entry.key(), String::new()).text);
+
+        if matches!(entry.value, Node::Map(_)) {
+            self.out.push(' ');
+        } else {
+            self.out.push_str(": ");
+        }
+        self.write_node(&entry.value, depth);
+
+        if let Some(comma) = &entry.comma {
+            self.pending.extend(self.comments.above(comma));
+            self.pending.extend(self.comments.trailing(comma));
+        }
+    }
+
+    // `node` from its first token on, `depth` levels deep; its leading
+    // comments are already written
+    fn write_node(&mut self, node: &Node, depth: usize) {
+        match node {
+            Node::Scalar(token) => {
+                let Node::Scalar(formatted) = format_node(&node.to_value(), depth, String::new())
+                else {
+                    unreachable!("scalars format as scalars")
+                };
+                self.token(token, &formatted.text);
+            }
+
+            Node::List(list) => {
+                self.token(&list.open, "[");
+
+                // lists of scalars stay on one line unless comments are in
+                // the way
+                let inline = list
+                    .items
+                    .iter()
+                    .all(|item| matches!(item.value, Node::Scalar(_)))
+                    && !self.comments.inside(node);
+
+                for (i, item) in list.items.iter().enumerate() {
+                    if inline {
+                        if i > 0 {
+                            self.out.push(' ');
+                        }
+                    } else {
+                        self.comment_lines(self.value_above(&item.value), depth + 1);
+                        self.line(depth + 1);
+                    }
+
+                    self.write_node(&item.value, depth + 1);
+
+                    if i + 1 < list.items.len() {
+                        self.out.push(',');
+                    }
+                    if let Some(comma) = &item.comma {
+                        self.pending.extend(self.comments.above(comma));
+                        self.pending.extend(self.comments.trailing(comma));
+                    }
+                }
+
+                if !inline {
+                    self.comment_lines(self.comments.above(&list.close), depth + 1);
+                    self.line(depth);
+                }
+                self.token(&list.close, "]");
+            }
+
+            Node::Map(map) => {
+                match &map.open {
+                    Some(open) => self.token(open, "{"),
+                    None => self.out.push('{'),
+                }
+
+                let entries = self.entries(map);
+                let close_above = match &map.close {
+                    Some(close) => self.comments.above(close),
+                    None => Vec::new(),
+                };
+                let empty = entries.is_empty() && close_above.is_empty();
+
+                for (entry, above) in entries {
+                    self.write_entry(entry, above, depth + 1);
+                }
+
+                if !empty {
+                    self.comment_lines(close_above, depth + 1);
+                    self.line(depth);
+                }
+                match &map.close {
+                    Some(close) => self.token(close, "}"),
+                    None => self.out.push('}'),
+                }
+            }
+        }
+    }
+}
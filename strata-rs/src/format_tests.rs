@@ -0,0 +1,244 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use proptest::prelude::*;
+
+    use crate::cst::{self, Document};
+    use crate::decode::decode;
+    use crate::format::{format_document, format_value};
+    use crate::parser::parse;
+    use crate::value::Value;
+    use crate::{bytes, int, list, map, null, string};
+
+    // values every current `.st` construct can express
+    fn arb_text_value() -> impl Strategy<Value = Value> {
+        let text = any::<String>();
+        let key = prop_oneof!["[A-Za-z_][A-Za-z0-9_]{0,8}", any::<String>()];
+
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::Int),
+            text.prop_map(Value::String),
+            prop::collection::vec(any::<u8>(), 0..32).prop_map(Value::Bytes),
+        ];
+
+        leaf.prop_recursive(4, 64, 8, move |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(Value::List),
+                prop::collection::btree_map(key.clone(), inner, 0..8).prop_map(Value::Map),
+            ]
+        })
+    }
+
+    fn assert_roundtrip(value: &Value) {
+        let text = format_value(value);
+        let reparsed = parse(&text).unwrap_or_else(|e| panic!("{:?} in\n{}", e, text));
+
+        assert_eq!(&reparsed, value, "roundtrip mismatch for\n{}", text);
+        assert_eq!(format_value(&reparsed), text, "not idempotent");
+    }
+
+    #[test]
+    fn format_scalars() {
+        assert_eq!(format_value(&null!()), "null\n");
+        assert_eq!(format_value(&int!(-7)), "-7\n");
+        assert_eq!(format_value(&bytes!([0xde, 0xad])), "0xdead\n");
+        assert_eq!(format_value(&bytes!([])), "0x\n");
+        assert_eq!(
+            format_value(&string!("a \"q\" \\ \n é 😀 \u{1b}")),
+            "\"a \\\"q\\\" \\\\ \\n é 😀 \\u001b\"\n"
+        );
+    }
+
+    #[test]
+    fn format_nested_layout() {
+        let value = map! {
+            "config" => map! {
+                "retries" => int!(3),
+                "tags" => list![string!("a"), string!("b")],
+                "inner" => map! { "x" => null!() },
+                "items" => list![map! { "value" => int!(1) }, list![]],
+                "empty" => map! {},
+            },
+        };
+
+        let expected = "\
+config {
+  empty {}
+  inner {
+    x: null
+  }
+  items: [
+    {
+      value: 1
+    },
+    []
+  ]
+  retries: 3
+  tags: [\"a\", \"b\"]
+}
+";
+
+        assert_eq!(format_value(&value), expected);
+        assert_roundtrip(&value);
+    }
+
+    #[test]
+    fn format_root_map_without_shorthand() {
+        let value = map! { "a" => int!(1), "b" => int!(2) };
+        assert_eq!(format_value(&value), "{\n  a: 1\n  b: 2\n}\n");
+
+        let value = map! { "a" => int!(1) };
+        assert_eq!(format_value(&value), "{\n  a: 1\n}\n");
+    }
+
+    #[test]
+    fn format_quoted_keys() {
+        let value = map! {
+            "content-type" => string!("text/plain"),
+            "null" => null!(),
+            "src/lib.rs" => map! { "size" => int!(3) },
+        };
+
+        let text = format_value(&value);
+        assert_eq!(
+            text,
+            "{\n  \"content-type\": \"text/plain\"\n  \"null\": null\n  \"src/lib.rs\" {\n    size: 3\n  }\n}\n"
+        );
+        assert_roundtrip(&value);
+
+        // a root shorthand is only used for identifier keys
+        let value = map! { "a-b" => map! {} };
+        assert_eq!(format_value(&value), "{\n  \"a-b\" {}\n}\n");
+    }
+
+    #[test]
+    fn format_roundtrips_all_vectors() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("vectors");
+
+        for version in ["v1", "v2"] {
+            for entry in fs::read_dir(root.join(version)).unwrap() {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_str().unwrap();
+
+                let value = if name.ends_with(".st") {
+                    parse(&fs::read_to_string(&path).unwrap()).unwrap()
+                } else if name.ends_with(".scb.hex") {
+                    let text = fs::read_to_string(&path).unwrap();
+                    decode(&hex::decode(text.trim()).unwrap()).unwrap()
+                } else {
+                    continue;
+                };
+
+                assert_roundtrip(&value);
+            }
+        }
+    }
+
+    fn format_text(source: &str) -> String {
+        let text = format_document(&cst::parse(source).unwrap());
+        let reparsed = cst::parse(&text).unwrap_or_else(|e| panic!("{:?} in\n{}", e, text));
+
+        assert_eq!(reparsed.to_value(), parse(source).unwrap());
+        assert_eq!(format_document(&reparsed), text, "not idempotent");
+        text
+    }
+
+    fn comments(document: &Document) -> Vec<String> {
+        let mut comments = Vec::new();
+        document.root.visit(&mut |token| {
+            comments.extend(token.comments().map(str::to_string));
+        });
+        comments.extend(document.eof.comments().map(str::to_string));
+        comments.sort();
+        comments
+    }
+
+    #[test]
+    fn format_document_keeps_comments() {
+        let source = "\
+# top
+config {
+  # about b
+  b: 2 # two
+  a: [1,2] # list
+  // about c
+  c { x: 1 } # c end
+  # before close
+}
+# end
+";
+
+        let expected = "\
+# top
+config {
+  a: [1, 2] # list
+  # about b
+  b: 2 # two
+  // about c
+  c {
+    x: 1
+  } # c end
+  # before close
+}
+# end
+";
+
+        assert_eq!(format_text(source), expected);
+    }
+
+    #[test]
+    fn format_document_moves_misplaced_comments() {
+        // a comment after a comma ends the element's line, and one inside
+        // an entry goes above it
+        assert_eq!(
+            format_text("{ y: [ # open\n 1, # one\n 2\n ], x: # colon\n 0x # empty\n , }"),
+            "{\n  # colon\n  x: 0x # empty\n  y: [ # open\n    1, # one\n    2\n  ]\n}\n"
+        );
+
+        // an overridden duplicate leaves its comments to the winner
+        assert_eq!(
+            format_text("{ a: 1 # first\n a: 2 # second\n}"),
+            "{\n  # first\n  a: 2 # second\n}\n"
+        );
+
+        assert_eq!(format_text("# only\n1 # one"), "# only\n1 # one\n");
+        assert_eq!(format_text("cfg { }  # end\n"), "cfg {} # end\n");
+    }
+
+    #[test]
+    fn format_document_without_comments_matches_format_value() {
+        let source = "{ b: b64\"AAE=\", a: { x: [1,2,] }, a: 0xFF, \"c\" {} }";
+        assert_eq!(format_text(source), format_value(&parse(source).unwrap()));
+    }
+
+    proptest! {
+        #[test]
+        fn format_roundtrips(value in arb_text_value()) {
+            assert_roundtrip(&value);
+
+            let text = format_value(&value);
+            prop_assert_eq!(format_document(&cst::parse(&text).unwrap()), text);
+        }
+
+        #[test]
+        fn format_document_keeps_every_comment(value in arb_text_value()) {
+            // a comment above and at the end of every line
+            let mut source = String::new();
+            for (i, line) in format_value(&value).lines().enumerate() {
+                source.push_str(&format!("# above {}\n{} // end {}\n", i, line, i));
+            }
+
+            let text = format_text(&source);
+            prop_assert_eq!(
+                comments(&cst::parse(&text).unwrap()),
+                comments(&cst::parse(&source).unwrap())
+            );
+        }
+    }
+}
@@ -1,9 +1,41 @@
-use crate::encode::encode;
+use std::io::{self, Read, Write};
+
+use crate::decode::decode;
+use crate::encode::{encode, encode_to_writer, encoded_len};
+use crate::error::{DecodeError, FrameError, StrataError};
 use crate::value::Value;
 
 const STRATA_MAGIC: &[u8; 7] = b"STRATA1";
 const STRATA_VERSION: u8 = 0x01;
 
+/// Header of a framed payload: magic followed by a version byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: u8,
+}
+
+impl FrameHeader {
+    pub const LEN: usize = STRATA_MAGIC.len() + 1;
+
+    pub fn parse(input: &[u8]) -> Result<Self, FrameError> {
+        let magic_len = input.len().min(STRATA_MAGIC.len());
+        if input[..magic_len] != STRATA_MAGIC[..magic_len] {
+            return Err(FrameError::BadMagic);
+        }
+
+        if input.len() < Self::LEN {
+            return Err(FrameError::TruncatedHeader);
+        }
+
+        let version = input[STRATA_MAGIC.len()];
+        if version != STRATA_VERSION {
+            return Err(FrameError::UnsupportedVersion(version));
+        }
+
+        Ok(Self { version })
+    }
+}
+
 pub fn encode_framed(value: &Value) -> Vec<u8> {
     let mut out = Vec::new();
 
@@ -13,3 +45,165 @@ pub fn encode_framed(value: &Value) -> Vec<u8> {
 
     out
 }
+
+/// Inverse of `encode_framed`.
+///
+/// Payload error offsets count from the start of `input`, header included.
+pub fn decode_framed(input: &[u8]) -> Result<(FrameHeader, Value), FrameError> {
+    let header = FrameHeader::parse(input)?;
+
+    let value = decode(&input[FrameHeader::LEN..]).map_err(|err| DecodeError {
+        offset: err.offset + FrameHeader::LEN,
+        ..err
+    })?;
+
+    Ok((header, value))
+}
+
+/// Largest payload accepted by length-delimited framing unless configured
+/// otherwise. Matches the Northstar T3 wire format.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+pub(crate) const LEN_PREFIX: usize = 4;
+
+pub(crate) fn check_frame_len(len: usize, max: usize) -> Result<(), FrameError> {
+    // the prefix cannot express more than u32::MAX
+    let max = max.min(u32::MAX as usize);
+
+    if len == 0 {
+        return Err(FrameError::EmptyFrame);
+    }
+    if len > max {
+        return Err(FrameError::FrameTooLarge { len, max });
+    }
+    Ok(())
+}
+
+/// Writes canonical payloads as length-delimited frames:
+/// a 4-byte big-endian length followed by the payload.
+pub struct FrameWriter<W> {
+    inner: W,
+    max_frame_len: usize,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Encode `value` straight into the sink; the size limit is checked
+    /// before any byte is written.
+    pub fn write_value(&mut self, value: &Value) -> Result<(), StrataError> {
+        let len = encoded_len(value);
+        check_frame_len(len, self.max_frame_len)?;
+
+        self.inner.write_all(&(len as u32).to_be_bytes())?;
+        encode_to_writer(value, &mut self.inner)
+    }
+
+    /// Frame already-encoded canonical bytes.
+    pub fn write_payload(&mut self, payload: &[u8]) -> Result<(), StrataError> {
+        check_frame_len(payload.len(), self.max_frame_len)?;
+
+        self.inner
+            .write_all(&(payload.len() as u32).to_be_bytes())?;
+        self.inner.write_all(payload)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads length-delimited frames written by `FrameWriter`.
+///
+/// Payload error offsets count from the start of the payload.
+pub struct FrameReader<R> {
+    inner: R,
+    max_frame_len: usize,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Next raw payload, or `None` at a clean end of stream.
+    pub fn read_payload(&mut self) -> Result<Option<Vec<u8>>, StrataError> {
+        let mut prefix = [0u8; LEN_PREFIX];
+        let mut filled = 0;
+
+        while filled < LEN_PREFIX {
+            match self.inner.read(&mut prefix[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(FrameError::TruncatedFrame.into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let len = u32::from_be_bytes(prefix) as usize;
+        check_frame_len(len, self.max_frame_len)?;
+
+        let mut payload = Vec::new();
+        (&mut self.inner)
+            .take(len as u64)
+            .read_to_end(&mut payload)?;
+
+        if payload.len() < len {
+            return Err(FrameError::TruncatedFrame.into());
+        }
+
+        Ok(Some(payload))
+    }
+
+    /// Next decoded value, or `None` at a clean end of stream.
+    pub fn read_value(&mut self) -> Result<Option<Value>, StrataError> {
+        match self.read_payload()? {
+            Some(payload) => Ok(Some(decode(&payload).map_err(FrameError::Decode)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = Result<Value, StrataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_value().transpose()
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::encode::encode;
+    use crate::error::{DecodeError, DecodeErrorKind, FrameError, StrataError};
+    use crate::framing::{FrameHeader, FrameReader, FrameWriter, decode_framed, encode_framed};
+    use crate::value::Value;
+
+    fn frame_err<T: std::fmt::Debug>(result: Result<T, StrataError>) -> FrameError {
+        match result {
+            Err(StrataError::Frame(e)) => e,
+            other => panic!("expected frame error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn framed_roundtrip() {
+        let value = Value::List(vec![Value::Int(1), Value::String("hi".into())]);
+
+        let (header, decoded) = decode_framed(&encode_framed(&value)).unwrap();
+
+        assert_eq!(header, FrameHeader { version: 0x01 });
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn framed_bad_magic() {
+        assert_eq!(decode_framed(b"STRATB1\x01\x00"), Err(FrameError::BadMagic));
+        assert_eq!(decode_framed(b"XY"), Err(FrameError::BadMagic));
+    }
+
+    #[test]
+    fn framed_truncated_header() {
+        assert_eq!(decode_framed(b""), Err(FrameError::TruncatedHeader));
+        assert_eq!(decode_framed(b"STRATA1"), Err(FrameError::TruncatedHeader));
+    }
+
+    #[test]
+    fn framed_unsupported_version() {
+        assert_eq!(
+            decode_framed(b"STRATA1\x02\x00"),
+            Err(FrameError::UnsupportedVersion(0x02))
+        );
+    }
+
+    #[test]
+    fn framed_payload_error_offset() {
+        // header + truncated string
+        let err = decode_framed(b"STRATA1\x01\x20\x05h").unwrap_err();
+
+        assert_eq!(
+            err,
+            FrameError::Decode(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEOF,
+                offset: 10,
+            })
+        );
+    }
+
+    // length-delimited frames
+    #[test]
+    fn length_delimited_wire_format() {
+        let mut writer = FrameWriter::new(Vec::new());
+        writer.write_value(&Value::Int(42)).unwrap();
+        writer.write_payload(&[0x00]).unwrap();
+
+        assert_eq!(
+            writer.into_inner(),
+            vec![0, 0, 0, 2, 0x10, 0x2a, 0, 0, 0, 1, 0x00]
+        );
+    }
+
+    #[test]
+    fn length_delimited_roundtrip() {
+        let values = vec![
+            Value::Int(42),
+            Value::String("hello".into()),
+            Value::List(vec![Value::Bytes(vec![1, 2, 3]), Value::Null]),
+        ];
+
+        let mut writer = FrameWriter::new(Vec::new());
+        for value in &values {
+            writer.write_value(value).unwrap();
+        }
+
+        let mut reader = FrameReader::new(Cursor::new(writer.into_inner()));
+        assert_eq!(
+            reader.read_payload().unwrap(),
+            Some(encode(&values[0]).unwrap())
+        );
+
+        let rest: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(rest, values[1..]);
+    }
+
+    #[test]
+    fn length_delimited_rejects_empty_frames() {
+        let mut writer = FrameWriter::new(Vec::new());
+        assert_eq!(frame_err(writer.write_payload(&[])), FrameError::EmptyFrame);
+
+        let mut reader = FrameReader::new(Cursor::new(vec![0, 0, 0, 0]));
+        assert_eq!(frame_err(reader.read_payload()), FrameError::EmptyFrame);
+    }
+
+    #[test]
+    fn length_delimited_max_frame_len() {
+        let value = Value::Bytes(vec![0; 8]);
+
+        let mut writer = FrameWriter::new(Vec::new()).max_frame_len(4);
+        assert_eq!(
+            frame_err(writer.write_value(&value)),
+            FrameError::FrameTooLarge { len: 10, max: 4 }
+        );
+        assert!(writer.get_ref().is_empty());
+
+        let mut reader =
+            FrameReader::new(Cursor::new(vec![0, 0, 0, 5, 0, 0, 0, 0, 0])).max_frame_len(4);
+        assert_eq!(
+            frame_err(reader.read_payload()),
+            FrameError::FrameTooLarge { len: 5, max: 4 }
+        );
+    }
+
+    #[test]
+    fn length_delimited_truncated() {
+        let mut reader = FrameReader::new(Cursor::new(vec![0, 0]));
+        assert_eq!(frame_err(reader.read_payload()), FrameError::TruncatedFrame);
+
+        let mut reader = FrameReader::new(Cursor::new(vec![0, 0, 0, 3, 0x10]));
+        assert_eq!(frame_err(reader.read_payload()), FrameError::TruncatedFrame);
+    }
+
+    #[test]
+    fn length_delimited_payload_error_offset() {
+        // frame holding a truncated string
+        let mut reader = FrameReader::new(Cursor::new(vec![0, 0, 0, 3, 0x20, 0x05, b'h']));
+
+        assert_eq!(
+            frame_err(reader.read_value()),
+            FrameError::Decode(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEOF,
+                offset: 2,
+            })
+        );
+    }
+}
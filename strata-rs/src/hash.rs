@@ -1,9 +1,13 @@
-use crate::encode::encode;
+use crate::encode::encode_to_writer;
 use crate::value::Value;
 
 /// Hash a Strata Value using canonical encoding
 /// Returns raw 32-byte BLAKE3 hash.
+///
+/// The encoding is streamed into the hasher and never materialized,
+/// so the digest equals `blake3::hash(&encode(value)?)` at no extra memory.
 pub fn hash_value(value: &Value) -> [u8; 32] {
-    let bytes = encode(value).unwrap();
-    blake3::hash(&bytes).into()
+    let mut hasher = blake3::Hasher::new();
+    encode_to_writer(value, &mut hasher).unwrap();
+    hasher.finalize().into()
 }
@@ -38,4 +38,15 @@ mod tests {
 
         assert_ne!(hash_value(&list_a), hash_value(&list_b));
     }
+
+    proptest::proptest! {
+        #[test]
+        fn hash_matches_hash_of_encoding(value in crate::test_strategies::arb_value()) {
+            use crate::encode::encode;
+            use crate::hash::hash_value;
+
+            let expected: [u8; 32] = blake3::hash(&encode(&value).unwrap()).into();
+            proptest::prop_assert_eq!(hash_value(&value), expected);
+        }
+    }
 }
@@ -1,3 +1,8 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use std::fmt;
+
 use crate::error::{ParseError, ParseErrorKind, Span};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,7 +35,30 @@ pub enum TokenKind {
     EOF,
 }
 
+/// Describes the token for error messages, e.g. "integer 3".
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Null => f.write_str("`null`"),
+            TokenKind::True => f.write_str("`true`"),
+            TokenKind::False => f.write_str("`false`"),
+            TokenKind::Int(number) => write!(f, "integer {}", number),
+            TokenKind::String(string) => write!(f, "string {:?}", string),
+            TokenKind::Bytes(_) => f.write_str("bytes literal"),
+            TokenKind::Ident(name) => write!(f, "identifier `{}`", name),
+            TokenKind::LBrace => f.write_str("'{'"),
+            TokenKind::RBrace => f.write_str("'}'"),
+            TokenKind::LBracket => f.write_str("'['"),
+            TokenKind::RBracket => f.write_str("']'"),
+            TokenKind::Colon => f.write_str("':'"),
+            TokenKind::Comma => f.write_str("','"),
+            TokenKind::EOF => f.write_str("end of input"),
+        }
+    }
+}
+
 pub struct Lexer<'a> {
+    source: &'a str,
     input: &'a [u8],
     offset: usize,
     line: usize,
@@ -40,6 +68,7 @@ pub struct Lexer<'a> {
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
+            source: input,
             input: input.as_bytes(),
             offset: 0,
             line: 1,
@@ -47,6 +76,11 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Byte offset just past the last token returned.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     fn span(&self) -> Span {
         Span {
             offset: self.offset,
@@ -66,6 +100,18 @@ impl<'a> Lexer<'a> {
         self.input.get(self.offset).copied()
     }
 
+    fn peek_char(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn bump_char(&mut self) -> Option<char> {
+        let ch = self.peek_char()?;
+        for _ in 0..ch.len_utf8() {
+            self.bump();
+        }
+        Some(ch)
+    }
+
     fn bump(&mut self) -> Option<u8> {
         let byte = self.peek()?;
         self.offset += 1;
@@ -73,7 +119,8 @@ impl<'a> Lexer<'a> {
         if byte == b'\n' {
             self.line += 1;
             self.column = 1;
-        } else {
+        } else if !matches!(byte, 0x80..=0xBF) {
+            // columns count characters, not UTF-8 continuation bytes
             self.column += 1;
         }
 
@@ -89,6 +136,15 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    // what may follow a literal that has no closing quote
+    fn at_delimiter(&self) -> bool {
+        match self.peek() {
+            None | Some(b' ' | b'\n' | b'\r' | b'\t' | b',' | b']' | b'}' | b'#') => true,
+            Some(b'/') => self.input.get(self.offset + 1) == Some(&b'/'),
+            _ => false,
+        }
+    }
+
     fn skip_ignored(&mut self) {
         loop {
             //skip whitespace
@@ -150,6 +206,7 @@ impl<'a> Lexer<'a> {
 
     fn lex_int(&mut self) -> Result<TokenKind, ParseError> {
         let start = self.offset;
+        let start_span = self.span();
 
         // opt leading '-'
         if self.peek() == Some(b'-') {
@@ -165,16 +222,18 @@ impl<'a> Lexer<'a> {
 
         // must have at least 1 digit
         if !saw_digit {
-            return Err(self.error(ParseErrorKind::IntegerOutOfRange));
+            return Err(ParseError {
+                kind: ParseErrorKind::InvalidCharacter('-'),
+                span: start_span,
+            });
         }
 
-        let slice = &self.input[start..self.offset];
-        let text = std::str::from_utf8(slice)
-            .map_err(|_| self.error(ParseErrorKind::IntegerOutOfRange))?;
-
-        let parsed_value = text
+        let parsed_value = self.source[start..self.offset]
             .parse::<i64>()
-            .map_err(|_| self.error(ParseErrorKind::IntegerOutOfRange))?;
+            .map_err(|_| ParseError {
+                kind: ParseErrorKind::IntegerOutOfRange,
+                span: start_span,
+            })?;
 
         Ok(TokenKind::Int(parsed_value))
     }
@@ -197,8 +256,17 @@ impl<'a> Lexer<'a> {
 
         let hex_len = self.offset - hex_start;
 
-        // must have even number of hex digits and at least one byte
-        if hex_len == 0 || !hex_len.is_multiple_of(2) {
+        // `0xzz` is a bad literal, not empty bytes followed by `zz`
+        if !self.at_delimiter() {
+            let err = self.error(ParseErrorKind::MalformedBytesLiteral);
+            while !self.at_delimiter() {
+                self.bump_char();
+            }
+            return Err(err);
+        }
+
+        // must have even number of hex digits; `0x` alone is empty bytes
+        if !hex_len.is_multiple_of(2) {
             return Err(self.error(ParseErrorKind::MalformedBytesLiteral));
         }
 
@@ -218,7 +286,163 @@ impl<'a> Lexer<'a> {
         Ok(TokenKind::Bytes(bytes))
     }
 
+    // b64"..." with the standard alphabet and canonical padding
+    fn lex_base64(&mut self) -> Result<TokenKind, ParseError> {
+        // consume b64"
+        for _ in 0..4 {
+            self.bump();
+        }
+
+        let data_start = self.offset;
+
+        while matches!(
+            self.peek(),
+            Some(b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'+' | b'/' | b'=')
+        ) {
+            self.bump();
+        }
+
+        let data = &self.input[data_start..self.offset];
+
+        if self.peek() != Some(b'"') {
+            let err = self.error(ParseErrorKind::MalformedBytesLiteral);
+            self.skip_string_rest();
+            return Err(err);
+        }
+
+        let decoded = STANDARD.decode(data).map_err(|_| ParseError {
+            kind: ParseErrorKind::MalformedBytesLiteral,
+            span: Span {
+                offset: data_start,
+                line: self.line,
+                column: self.column - data.len(),
+            },
+        });
+
+        self.bump(); // closing '"'
+        Ok(TokenKind::Bytes(decoded?))
+    }
+
+    // the escape as written so far, starting at its backslash
+    fn escape_error(&self, start: Span) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::InvalidEscape(self.source[start.offset..self.offset].to_string()),
+            span: start,
+        }
+    }
+
+    fn lex_hex_digits(&mut self, count: usize, start: Span) -> Result<u32, ParseError> {
+        let mut value = 0u32;
+
+        for _ in 0..count {
+            let digit = self
+                .peek()
+                .and_then(Self::hex_digit)
+                .ok_or_else(|| self.escape_error(start))?;
+            self.bump();
+            value = (value << 4) | (digit as u32);
+        }
+
+        Ok(value)
+    }
+
+    // after '\u': either \u{X..XXXXXX} or \uXXXX, where a high surrogate
+    // must be followed by a \uXXXX low surrogate
+    fn lex_unicode_escape(&mut self, start: Span) -> Result<char, ParseError> {
+        let codepoint = if self.peek() == Some(b'{') {
+            self.bump();
+
+            let mut codepoint = 0u32;
+            let mut digits = 0;
+
+            while let Some(digit) = self.peek().and_then(Self::hex_digit) {
+                self.bump();
+                codepoint = (codepoint << 4) | (digit as u32);
+                digits += 1;
+
+                if digits > 6 {
+                    return Err(self.escape_error(start));
+                }
+            }
+
+            if digits == 0 || self.peek() != Some(b'}') {
+                return Err(self.escape_error(start));
+            }
+            self.bump();
+
+            codepoint
+        } else {
+            let unit = self.lex_hex_digits(4, start)?;
+
+            if (0xD800..=0xDBFF).contains(&unit) {
+                for expected in [b'\\', b'u'] {
+                    if self.peek() != Some(expected) {
+                        return Err(self.escape_error(start));
+                    }
+                    self.bump();
+                }
+
+                let low = self.lex_hex_digits(4, start)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.escape_error(start));
+                }
+
+                0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+            } else {
+                unit
+            }
+        };
+
+        // rejects lone surrogates and values above U+10FFFF
+        char::from_u32(codepoint).ok_or_else(|| self.escape_error(start))
+    }
+
+    // after '\\'
+    fn lex_escape(&mut self) -> Result<char, ParseError> {
+        let escape_start = self.span();
+        self.bump();
+
+        match self.bump_char().unwrap() {
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            'u' => self.lex_unicode_escape(escape_start),
+            _ => Err(self.escape_error(escape_start)),
+        }
+    }
+
+    // skip what is left of a bad string or base64 literal, so lexing can
+    // resume after it
+    fn skip_string_rest(&mut self) {
+        while let Some(current_byte) = self.peek() {
+            match current_byte {
+                b'\n' | b'\r' => return,
+                b'"' => {
+                    self.bump();
+                    return;
+                }
+                b'\\' => {
+                    self.bump();
+                    if !matches!(self.peek(), None | Some(b'\n' | b'\r')) {
+                        self.bump_char();
+                    }
+                }
+                _ => {
+                    self.bump_char();
+                }
+            }
+        }
+    }
+
     fn lex_string(&mut self) -> Result<TokenKind, ParseError> {
+        let start = self.span();
+        let unterminated = ParseError {
+            kind: ParseErrorKind::UnterminatedString,
+            span: start,
+        };
+
         self.bump(); // opening '"'
 
         let mut out = String::new();
@@ -232,60 +456,38 @@ impl<'a> Lexer<'a> {
                 }
 
                 b'\\' => {
-                    //escape seq
-                    self.bump();
-                    let escape_char = self
-                        .bump()
-                        .ok_or_else(|| self.error(ParseErrorKind::MalformedBytesLiteral))?;
-
-                    match escape_char {
-                        b'"' => out.push('"'),
-                        b'\\' => out.push('\\'),
-                        b'n' => out.push('\n'),
-                        b'r' => out.push('\r'),
-                        b't' => out.push('\t'),
-
-                        b'u' => {
-                            // \uXXXX
-                            let mut codepoint = 0u32;
-
-                            for _ in 0..4 {
-                                let hex_byte = self.bump().ok_or_else(|| {
-                                    self.error(ParseErrorKind::MalformedBytesLiteral)
-                                })?;
-                                let digit = Self::hex_digit(hex_byte).ok_or_else(|| {
-                                    self.error(ParseErrorKind::MalformedBytesLiteral)
-                                })?;
-                                codepoint = (codepoint << 4) | (digit as u32);
-                            }
-
-                            let unicode_char = char::from_u32(codepoint)
-                                .ok_or_else(|| self.error(ParseErrorKind::MalformedBytesLiteral))?;
-                            out.push(unicode_char);
-                        }
-                        _ => return Err(self.error(ParseErrorKind::MalformedBytesLiteral)),
+                    if matches!(self.input.get(self.offset + 1), None | Some(b'\n' | b'\r')) {
+                        // the backslash is part of the string; the line
+                        // break is left to end it
+                        self.bump();
+                        return Err(unterminated);
                     }
+
+                    let escaped = self.lex_escape().inspect_err(|_| self.skip_string_rest())?;
+                    out.push(escaped);
                 }
 
                 b'\n' | b'\r' => {
                     // strings cant span lines
-                    return Err(self.error(ParseErrorKind::MalformedBytesLiteral));
+                    return Err(unterminated);
                 }
 
-                _ => {
-                    if current_byte >= 0x80 {
-                        return Err(self.error(ParseErrorKind::MalformedBytesLiteral));
-                    }
+                0x80.. => {
+                    out.push(self.bump_char().unwrap());
+                }
 
+                _ => {
                     out.push(current_byte as char);
                     self.bump();
                 }
             }
         }
 
-        Err(self.error(ParseErrorKind::MalformedBytesLiteral))
+        Err(unterminated)
     }
 
+    /// After an error the offending input has been skipped, so calling
+    /// `next_token` again resumes with the input that follows it.
     pub fn next_token(&mut self) -> Result<Token, ParseError> {
         self.skip_ignored();
 
@@ -333,6 +535,9 @@ impl<'a> Lexer<'a> {
             // bytes literal
             b'0' if self.input.get(self.offset + 1) == Some(&b'x') => self.lex_bytes()?,
 
+            // base64 bytes literal
+            b'b' if self.input[self.offset..].starts_with(b"b64\"") => self.lex_base64()?,
+
             // integer literal
             b'-' | b'0'..=b'9' => self.lex_int()?,
 
@@ -340,10 +545,10 @@ impl<'a> Lexer<'a> {
             b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.lex_identifier(),
 
             _ => {
-                return Err(self.error(ParseErrorKind::UnexpectedToken {
-                    expected: "valid token",
-                    found: "invalid character",
-                }));
+                let ch = self.peek_char().unwrap();
+                let err = self.error(ParseErrorKind::InvalidCharacter(ch));
+                self.bump_char();
+                return Err(err);
             }
         };
 
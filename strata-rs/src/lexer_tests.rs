@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod tests {
+    use crate::error::{ParseError, ParseErrorKind};
     use crate::lexer::*;
 
     #[test]
@@ -91,4 +92,164 @@ mod tests {
 
         assert_eq!(lexer.next_token().unwrap().kind, EOF);
     }
+
+    #[test]
+    fn lex_utf8_string() {
+        let mut lexer = Lexer::new("\"café 日本 😀\" x");
+
+        assert_eq!(
+            lexer.next_token().unwrap().kind,
+            TokenKind::String("café 日本 😀".into())
+        );
+
+        // columns count characters
+        assert_eq!(lexer.next_token().unwrap().span.column, 13);
+    }
+
+    #[test]
+    fn lex_unicode_escapes() {
+        let mut lexer = Lexer::new(r#""\u00e9 \u{1F600} \u{e9} \uD83D\uDE00""#);
+
+        assert_eq!(
+            lexer.next_token().unwrap().kind,
+            TokenKind::String("é 😀 é 😀".into())
+        );
+    }
+
+    #[test]
+    fn lex_invalid_unicode_escapes() {
+        for input in [
+            r#""\uD83D""#,
+            r#""\uDE00""#,
+            r#""\uD83D\u0041""#,
+            r#""\u{110000}""#,
+            r#""\u{D800}""#,
+            r#""\u{}""#,
+            r#""\u{1234567}""#,
+            r#""\u{41""#,
+        ] {
+            assert!(Lexer::new(input).next_token().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn lex_empty_bytes() {
+        let mut lexer = Lexer::new("[0x, 0x]");
+
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::LBracket);
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Bytes(vec![]));
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Comma);
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Bytes(vec![]));
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::RBracket);
+
+        assert!(Lexer::new("0x0").next_token().is_err());
+    }
+
+    #[test]
+    fn lex_bytes_needs_delimiter() {
+        for input in ["0xzz", "0xabzz", "0x_", "0xab\u{e9}"] {
+            let err = Lexer::new(input).next_token().unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::MalformedBytesLiteral, "{}", input);
+        }
+
+        // the error points past the digits, and the whole word is skipped
+        let mut lexer = Lexer::new("[0xzz]");
+        lexer.next_token().unwrap();
+        assert_eq!(lexer.next_token().unwrap_err().span.offset, 3);
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::RBracket);
+
+        let mut lexer = Lexer::new("0xff// c\n");
+        assert_eq!(
+            lexer.next_token().unwrap().kind,
+            TokenKind::Bytes(vec![0xff])
+        );
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::EOF);
+    }
+
+    #[test]
+    fn lex_base64_bytes() {
+        let mut lexer = Lexer::new(r#"b64"3q2+7w==" b64"" b64x"#);
+
+        assert_eq!(
+            lexer.next_token().unwrap().kind,
+            TokenKind::Bytes(vec![0xDE, 0xAD, 0xBE, 0xEF])
+        );
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Bytes(vec![]));
+        assert_eq!(
+            lexer.next_token().unwrap().kind,
+            TokenKind::Ident("b64x".into())
+        );
+    }
+
+    #[test]
+    fn lex_invalid_base64_bytes() {
+        for input in [
+            r#"b64"3q2+7w""#,
+            r#"b64"3q2+7x==""#,
+            r#"b64"3q2 7w==""#,
+            r#"b64"3q2-7w==""#,
+            r#"b64"3q2+7w=="#,
+        ] {
+            assert!(Lexer::new(input).next_token().is_err(), "{}", input);
+        }
+    }
+
+    fn lex_error(input: &str) -> ParseError {
+        let mut lexer = Lexer::new(input);
+        loop {
+            match lexer.next_token() {
+                Ok(token) if token.kind == TokenKind::EOF => panic!("no error in {}", input),
+                Ok(_) => continue,
+                Err(err) => return err,
+            }
+        }
+    }
+
+    #[test]
+    fn lex_error_kinds() {
+        let err = lex_error(r#"a: "x\qy""#);
+        assert_eq!(err.kind, ParseErrorKind::InvalidEscape(r"\q".into()));
+        assert_eq!(err.span.column, 6);
+
+        let err = lex_error(r#""\u{110000}""#);
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::InvalidEscape(r"\u{110000}".into())
+        );
+        assert_eq!(
+            err.to_string(),
+            r"invalid escape `\u{110000}` in string at line 1, column 2"
+        );
+
+        let err = lex_error(r#""\uD83D!""#);
+        assert_eq!(err.kind, ParseErrorKind::InvalidEscape(r"\uD83D".into()));
+
+        let err = lex_error(r#""\é""#);
+        assert_eq!(err.kind, ParseErrorKind::InvalidEscape(r"\é".into()));
+
+        let err = lex_error("x: \"abc\ny: 1");
+        assert_eq!(err.kind, ParseErrorKind::UnterminatedString);
+        assert_eq!((err.span.line, err.span.column), (1, 4));
+
+        assert_eq!(
+            lex_error(r#""abc\"#).kind,
+            ParseErrorKind::UnterminatedString
+        );
+        assert_eq!(
+            lex_error(r#""abc"#).kind,
+            ParseErrorKind::UnterminatedString
+        );
+
+        let err = lex_error("a: 1\nb: @");
+        assert_eq!(err.kind, ParseErrorKind::InvalidCharacter('@'));
+        assert_eq!((err.span.line, err.span.column), (2, 4));
+        assert_eq!(err.kind.to_string(), "invalid character '@'");
+
+        assert_eq!(lex_error("é").kind, ParseErrorKind::InvalidCharacter('é'));
+        assert_eq!(lex_error("- 1").kind, ParseErrorKind::InvalidCharacter('-'));
+        assert_eq!(
+            lex_error("99999999999999999999").kind,
+            ParseErrorKind::IntegerOutOfRange
+        );
+    }
 }
@@ -1,19 +1,50 @@
 mod macros;
 pub mod value;
 
+#[cfg(feature = "tokio")]
+pub mod codec;
+pub mod convert;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod decode;
+pub mod diagnostic;
 pub mod encode;
 pub mod error;
+pub mod format;
 pub mod framing;
 pub mod hash;
 pub mod lexer;
 pub mod parser;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod spanned;
+pub mod stream;
 
+#[cfg(feature = "serde")]
+pub use de::{from_slice, from_value};
+#[cfg(feature = "serde")]
+pub use ser::{to_value, to_vec};
+#[cfg(feature = "derive")]
+pub use strata_derive::{FromStrata, ToStrata};
+
+mod codec_tests;
+mod convert_tests;
+mod cst_tests;
 mod decode_tests;
+mod diagnostic_tests;
 mod encode_tests;
+mod error_tests;
+mod format_tests;
+mod framing_tests;
 mod hash_tests;
 mod lexer_tests;
 mod macros_tests;
 mod parser_tests;
 mod roundtrip_tests;
 mod semantic_vectors;
+mod serde_tests;
+mod spanned_tests;
+mod stream_tests;
+mod test_strategies;
+mod value_tests;
@@ -1,242 +1,393 @@
-use crate::error::{ParseError, ParseErrorKind};
-use crate::lexer::{Lexer, Token, TokenKind};
+use crate::cst::{self, Document, Entry, Item, List, Map, Node, SyntaxToken};
+use crate::error::{Expected, ParseError, ParseErrorKind, Span};
+use crate::lexer::{Lexer, TokenKind};
 use crate::value::Value;
-use std::collections::BTreeMap;
 
+/// The one Strata Text grammar. It builds the lossless syntax tree, which
+/// [`parse`] turns into a value and [`cst::parse`] returns as it is.
 pub struct Parser<'a> {
+    source: &'a str,
     lexer: Lexer<'a>,
-    lookahead: Token,
+    // end of the last token, where the next token's trivia starts
+    end: usize,
+    lookahead: SyntaxToken,
+
+    // recovering mode collects errors instead of returning the first one
+    recovering: bool,
+    errors: Vec<ParseError>,
+}
+
+// what follows an entry or element
+enum Separator {
+    Comma(SyntaxToken),
+    // the closing token, or in a map a key on the next line
+    Implicit,
+    // the container is missing its closing token; leave the rest to the parent
+    Abandon,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Result<Self, ParseError> {
-        let mut lexer = Lexer::new(input);
-        let lookahead = lexer.next_token()?;
+        let mut parser = Self::with_recovery(input, false);
+        parser.advance()?;
+        Ok(parser)
+    }
+
+    fn with_recovery(input: &'a str, recovering: bool) -> Self {
+        Self {
+            source: input,
+            lexer: Lexer::new(input),
+            end: 0,
+            lookahead: SyntaxToken::new(TokenKind::EOF, "", ""),
+            recovering,
+            errors: Vec::new(),
+        }
+    }
+
+    // move to the next token and return the one that was the lookahead
+    fn advance(&mut self) -> Result<SyntaxToken, ParseError> {
+        loop {
+            let err = match self.lexer.next_token() {
+                Ok(token) => return Ok(self.replace_lookahead(token.kind, token.span)),
+                Err(err) if self.recovering => err,
+                Err(err) => return Err(err),
+            };
+
+            // a bad literal is replaced by a placeholder so the syntax around
+            // it still parses; a stray character is skipped
+            let placeholder = match err.kind {
+                ParseErrorKind::UnterminatedString | ParseErrorKind::InvalidEscape(_) => {
+                    Some(TokenKind::String(String::new()))
+                }
+                ParseErrorKind::MalformedBytesLiteral => Some(TokenKind::Bytes(Vec::new())),
+                ParseErrorKind::IntegerOutOfRange => Some(TokenKind::Int(0)),
+                _ => None,
+            };
+
+            let span = err.span;
+            self.record(err);
 
-        Ok(Self { lexer, lookahead })
+            if let Some(kind) = placeholder {
+                return Ok(self.replace_lookahead(kind, span));
+            }
+        }
     }
 
-    fn advance(&mut self) -> Result<(), ParseError> {
-        self.lookahead = self.lexer.next_token()?;
-        Ok(())
+    fn replace_lookahead(&mut self, kind: TokenKind, span: Span) -> SyntaxToken {
+        let end = self.lexer.offset();
+        // a placeholder's span may point into the literal it replaces
+        let start = span.offset.clamp(self.end, end);
+
+        let next = SyntaxToken {
+            kind,
+            text: self.source[start..end].to_string(),
+            leading_trivia: self.source[self.end..start].to_string(),
+            span,
+        };
+        self.end = end;
+
+        std::mem::replace(&mut self.lookahead, next)
     }
 
-    fn expect(&mut self, kind: TokenKind) -> Result<(), ParseError> {
-        if self.lookahead.kind == kind {
-            self.advance()
-        } else {
-            Err(ParseError {
-                kind: ParseErrorKind::UnexpectedToken {
-                    expected: "different token",
-                    found: "unexpected token",
-                },
-                span: self.lookahead.span,
-            })
+    // one error per position: a failure that several levels notice is
+    // reported once
+    fn record(&mut self, err: ParseError) {
+        let repeated = self
+            .errors
+            .last()
+            .is_some_and(|last| last.span.offset == err.span.offset);
+
+        if !repeated {
+            self.errors.push(err);
         }
     }
 
-    fn parse_list(&mut self) -> Result<Value, ParseError> {
-        self.expect(TokenKind::LBracket)?;
+    fn recover(&mut self, err: ParseError, in_map: bool) -> Result<(), ParseError> {
+        if !self.recovering {
+            return Err(err);
+        }
 
-        let mut items = Vec::new();
+        self.record(err);
+        self.synchronize(in_map)
+    }
+
+    // Skip to the next ',', '}' or ']' of the current container. In a map,
+    // a key at the start of a later line also ends the skip, since entries
+    // are usually separated by newlines alone.
+    fn synchronize(&mut self, in_map: bool) -> Result<(), ParseError> {
+        let error_line = self.lookahead.span.line;
+        let mut depth = 0usize;
+
+        loop {
+            match self.lookahead.kind {
+                TokenKind::EOF => return Ok(()),
+
+                TokenKind::Comma | TokenKind::RBrace | TokenKind::RBracket if depth == 0 => {
+                    return Ok(());
+                }
+
+                TokenKind::Ident(_) | TokenKind::String(_)
+                    if depth == 0 && in_map && self.lookahead.span.line > error_line =>
+                {
+                    return Ok(());
+                }
 
-        // empty list
-        if self.lookahead.kind == TokenKind::RBracket {
-            self.advance()?; // consume ']'
-            return Ok(Value::List(items));
+                TokenKind::LBrace | TokenKind::LBracket => depth += 1,
+                TokenKind::RBrace | TokenKind::RBracket => depth -= 1,
+                _ => {}
+            }
+
+            self.advance()?;
         }
+    }
+
+    fn separator(&mut self, close: TokenKind, expected: Expected) -> Result<Separator, ParseError> {
+        let in_map = close == TokenKind::RBrace;
+        let mut recovered = false;
 
         loop {
-            // parse value
-            let element = self.parse_value()?;
-            items.push(element);
+            match &self.lookahead.kind {
+                // a trailing comma is allowed
+                TokenKind::Comma => return Ok(Separator::Comma(self.advance()?)),
 
-            match self.lookahead.kind {
-                TokenKind::Comma => {
-                    self.advance()?; // consume ','
+                kind if *kind == close => return Ok(Separator::Implicit),
 
-                    // allow trailing comma
-                    if self.lookahead.kind == TokenKind::RBracket {
-                        break;
-                    }
+                // implicit separator via newline
+                TokenKind::Ident(_) | TokenKind::String(_) if in_map => {
+                    return Ok(Separator::Implicit);
                 }
 
-                TokenKind::RBracket => break,
+                _ if recovered => return Ok(Separator::Abandon),
 
                 _ => {
-                    return Err(ParseError {
-                        kind: ParseErrorKind::UnexpectedToken {
-                            expected: "',' or ']'",
-                            found: "token",
-                        },
-                        span: self.lookahead.span,
-                    });
+                    let err = self.unexpected(expected.clone());
+                    self.recover(err, in_map)?;
+                    recovered = true;
                 }
             }
         }
-
-        self.expect(TokenKind::RBracket)?;
-        Ok(Value::List(items))
     }
 
-    fn parse_map(&mut self) -> Result<Value, ParseError> {
-        self.expect(TokenKind::LBrace)?;
+    // error at the lookahead token, which is described as what was found
+    fn unexpected(&self, expected: Expected) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::UnexpectedToken {
+                expected,
+                found: self.lookahead.kind.clone(),
+            },
+            span: self.lookahead.span,
+        }
+    }
 
-        let mut map = BTreeMap::new();
+    fn expect(&mut self, kind: TokenKind) -> Result<SyntaxToken, ParseError> {
+        if self.lookahead.kind == kind {
+            self.advance()
+        } else {
+            Err(self.unexpected(Expected::Token(kind)))
+        }
+    }
 
-        // empty map
-        if self.lookahead.kind == TokenKind::RBrace {
-            self.advance()?; // consume '}'
-            return Ok(Value::Map(map));
+    // stands in for the closing token of a container abandoned while
+    // recovering
+    fn missing(&self, kind: TokenKind) -> SyntaxToken {
+        SyntaxToken {
+            span: self.lookahead.span,
+            ..SyntaxToken::new(kind, "", "")
         }
+    }
 
-        loop {
-            // key must be identifier
-            let key = match &self.lookahead.kind {
-                TokenKind::Ident(name) => {
-                    let key = name.clone();
-                    self.advance()?;
-                    key
-                }
+    fn parse_list(&mut self) -> Result<Node, ParseError> {
+        let open = self.expect(TokenKind::LBracket)?;
+        let mut items = Vec::new();
 
-                _ => {
-                    return Err(ParseError {
-                        kind: ParseErrorKind::UnexpectedToken {
-                            expected: "identifier",
-                            found: "token",
-                        },
-                        span: self.lookahead.span,
-                    });
+        while self.lookahead.kind != TokenKind::RBracket {
+            let value = match self.parse_value() {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    self.recover(err, false)?;
+                    None
                 }
             };
 
-            let value = if self.lookahead.kind == TokenKind::LBrace {
-                // shorthand entry: key { ... }
-                self.parse_map()?
-            } else {
-                // normal entry: key : value
-                self.expect(TokenKind::Colon)?;
-                self.parse_value()?
+            let comma = match self.separator(TokenKind::RBracket, Expected::ListSeparator)? {
+                Separator::Comma(comma) => Some(comma),
+                Separator::Implicit => None,
+                Separator::Abandon => {
+                    items.extend(value.map(|value| Item { value, comma: None }));
+                    let close = self.missing(TokenKind::RBracket);
+                    return Ok(Node::List(List { open, items, close }));
+                }
             };
 
-            map.insert(key, value);
-
-            match self.lookahead.kind {
-                TokenKind::Comma => {
-                    self.advance()?;
+            items.extend(value.map(|value| Item { value, comma }));
+        }
 
-                    // allow trailing comma
-                    if self.lookahead.kind == TokenKind::RBrace {
-                        break;
-                    }
-                }
+        let close = self.advance()?;
+        Ok(Node::List(List { open, items, close }))
+    }
 
-                TokenKind::RBrace => break,
+    fn parse_map(&mut self) -> Result<Node, ParseError> {
+        let open = self.expect(TokenKind::LBrace)?;
+        let mut entries = Vec::new();
 
-                TokenKind::Ident(_) => {
-                    // implicit separator via newline
-                    continue;
+        while self.lookahead.kind != TokenKind::RBrace {
+            let entry = match self.parse_entry() {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    self.recover(err, true)?;
+                    None
                 }
+            };
 
-                _ => {
-                    return Err(ParseError {
-                        kind: ParseErrorKind::UnexpectedToken {
-                            expected: "',' or '}'",
-                            found: "token",
-                        },
-                        span: self.lookahead.span,
-                    });
+            let comma = match self.separator(TokenKind::RBrace, Expected::EntrySeparator)? {
+                Separator::Comma(comma) => Some(comma),
+                Separator::Implicit => None,
+                Separator::Abandon => {
+                    entries.extend(entry);
+                    return Ok(Node::Map(Map {
+                        open: Some(open),
+                        entries,
+                        close: Some(self.missing(TokenKind::RBrace)),
+                    }));
                 }
-            }
+            };
+
+            entries.extend(entry.map(|entry| Entry { comma, ..entry }));
         }
 
-        self.expect(TokenKind::RBrace)?;
-        Ok(Value::Map(map))
+        let close = self.advance()?;
+        Ok(Node::Map(Map {
+            open: Some(open),
+            entries,
+            close: Some(close),
+        }))
     }
 
-    fn parse_value(&mut self) -> Result<Value, ParseError> {
-        match &self.lookahead.kind {
-            TokenKind::Null => {
-                self.advance()?;
-                Ok(Value::Null)
-            }
-
-            TokenKind::True => {
-                self.advance()?;
-                Ok(Value::Bool(true))
-            }
-
-            TokenKind::False => {
-                self.advance()?;
-                Ok(Value::Bool(false))
-            }
+    fn parse_entry(&mut self) -> Result<Entry, ParseError> {
+        // key is an identifier or a quoted string
+        let name = match &self.lookahead.kind {
+            TokenKind::Ident(name) | TokenKind::String(name) => name.clone(),
+            _ => return Err(self.unexpected(Expected::MapKey)),
+        };
+        let key = self.advance()?;
+
+        // shorthand entry: key { ... }
+        if self.lookahead.kind == TokenKind::LBrace {
+            return Ok(Entry {
+                key,
+                colon: None,
+                value: self.parse_map()?,
+                comma: None,
+            });
+        }
 
-            TokenKind::Int(number) => {
-                let v = *number;
-                self.advance()?;
-                Ok(Value::Int(v))
-            }
+        // normal entry: key : value
+        if self.lookahead.kind != TokenKind::Colon {
+            return Err(self.unexpected(Expected::Colon { key: name }));
+        }
 
-            TokenKind::String(string) => {
-                let v = string.clone();
-                self.advance()?;
-                Ok(Value::String(v))
-            }
+        Ok(Entry {
+            key,
+            colon: Some(self.advance()?),
+            value: self.parse_value()?,
+            comma: None,
+        })
+    }
 
-            TokenKind::Bytes(bytes) => {
-                let v = bytes.clone();
-                self.advance()?;
-                Ok(Value::Bytes(v))
-            }
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        match &self.lookahead.kind {
+            TokenKind::Null
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::Int(_)
+            | TokenKind::String(_)
+            | TokenKind::Bytes(_) => Ok(Node::Scalar(self.advance()?)),
 
             TokenKind::LBracket => self.parse_list(),
             TokenKind::LBrace => self.parse_map(),
 
+            // identifier followed by '{' -> shorthand
             TokenKind::Ident(name) => {
-                let key = name.clone();
-                self.advance()?;
-
-                // identifier followed by '{' -> shorthand
-                if self.lookahead.kind == TokenKind::LBrace {
-                    let inner = self.parse_map()?;
-                    let mut map = BTreeMap::new();
-                    map.insert(key, inner);
-                    Ok(Value::Map(map))
-                } else {
-                    Err(ParseError {
-                        kind: ParseErrorKind::UnexpectedToken {
-                            expected: "map or value",
-                            found: "identifier",
-                        },
-                        span: self.lookahead.span,
-                    })
+                let name = name.clone();
+                let key = self.advance()?;
+
+                if self.lookahead.kind != TokenKind::LBrace {
+                    return Err(self.unexpected(Expected::ShorthandBrace { key: name }));
                 }
+
+                Ok(Node::Map(Map {
+                    open: None,
+                    entries: vec![Entry {
+                        key,
+                        colon: None,
+                        value: self.parse_map()?,
+                        comma: None,
+                    }],
+                    close: None,
+                }))
             }
 
-            _ => Err(ParseError {
-                kind: ParseErrorKind::UnexpectedToken {
-                    expected: "value",
-                    found: "token",
-                },
-                span: self.lookahead.span,
-            }),
+            _ => Err(self.unexpected(Expected::Value)),
         }
     }
 }
 
 pub fn parse(input: &str) -> Result<Value, ParseError> {
+    Ok(cst::parse(input)?.to_value())
+}
+
+// the syntax tree behind `cst::parse`
+pub(crate) fn parse_document(input: &str) -> Result<Document, ParseError> {
     let mut parser = Parser::new(input)?;
-    let parsed_value = parser.parse_value()?;
+    let root = parser.parse_value()?;
 
     if parser.lookahead.kind != TokenKind::EOF {
-        return Err(ParseError {
-            kind: ParseErrorKind::UnexpectedToken {
-                expected: "EOF",
-                found: "extra input",
-            },
-            span: parser.lookahead.span,
-        });
+        return Err(parser.unexpected(Expected::EndOfInput));
     }
 
-    Ok(parsed_value)
+    Ok(Document {
+        root,
+        eof: parser.lookahead,
+    })
+}
+
+/// Result of [`parse_recovering`].
+#[derive(Debug)]
+pub struct Recovered {
+    /// What could be parsed. Entries and elements that failed are left
+    /// out; a bad string, bytes or integer literal is kept as an empty
+    /// string, empty bytes or `0` so its entry survives.
+    pub value: Option<Value>,
+    /// Every error in source order. Empty when the input is valid.
+    pub errors: Vec<ParseError>,
+}
+
+/// Parse the whole input, collecting every error instead of stopping at
+/// the first.
+///
+/// After an error the parser skips to the next `,`, `}` or `]` (or, inside
+/// a map, to a key on a later line) and carries on.
+pub fn parse_recovering(input: &str) -> Recovered {
+    let mut parser = Parser::with_recovery(input, true);
+
+    // never fails while recovering
+    let _ = parser.advance();
+
+    let value = match parser.parse_value() {
+        Ok(root) => Some(root.to_value()),
+        Err(err) => {
+            parser.record(err);
+            None
+        }
+    };
+
+    if parser.lookahead.kind != TokenKind::EOF {
+        let err = parser.unexpected(Expected::EndOfInput);
+        parser.record(err);
+    }
+
+    Recovered {
+        value,
+        errors: parser.errors,
+    }
 }
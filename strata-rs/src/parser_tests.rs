@@ -1,6 +1,10 @@
 #[cfg(test)]
 mod tests {
-    use crate::parser::parse;
+    use proptest::prelude::*;
+
+    use crate::error::{Expected, ParseErrorKind};
+    use crate::lexer::TokenKind;
+    use crate::parser::{parse, parse_recovering};
     use crate::value::Value;
 
     #[test]
@@ -203,4 +207,270 @@ mod tests {
 
         assert_eq!(value.unwrap(), Value::Map(root_map));
     }
+
+    #[test]
+    fn parse_quoted_keys() {
+        let input = r#"
+            {
+                "content-type": "text/plain"
+                "src/main.rs" { size: 10 }
+                "1st key": 1, "": null
+                "a b": true
+            }
+        "#;
+
+        let value = parse(input);
+
+        use std::collections::BTreeMap;
+
+        let mut file_map = BTreeMap::new();
+        file_map.insert("size".into(), Value::Int(10));
+
+        let mut root_map = BTreeMap::new();
+        root_map.insert("content-type".into(), Value::String("text/plain".into()));
+        root_map.insert("src/main.rs".into(), Value::Map(file_map));
+        root_map.insert("1st key".into(), Value::Int(1));
+        root_map.insert("".into(), Value::Null);
+        root_map.insert("a b".into(), Value::Bool(true));
+
+        assert_eq!(value.unwrap(), Value::Map(root_map));
+    }
+
+    #[test]
+    fn quoted_key_requires_colon_or_brace() {
+        assert!(parse(r#"{ "a" 1 }"#).is_err());
+    }
+
+    fn unexpected(input: &str) -> (String, usize, usize) {
+        let err = parse(input).unwrap_err();
+        assert!(matches!(err.kind, ParseErrorKind::UnexpectedToken { .. }));
+        (err.kind.to_string(), err.span.line, err.span.column)
+    }
+
+    #[test]
+    fn unexpected_token_messages() {
+        assert_eq!(
+            unexpected("{\n  retries 3\n}"),
+            (
+                "expected ':' after key `retries`, found integer 3".into(),
+                2,
+                11
+            )
+        );
+        assert_eq!(
+            unexpected("[1 2]"),
+            (
+                "expected ',' or ']' after list element, found integer 2".into(),
+                1,
+                4
+            )
+        );
+        assert_eq!(
+            unexpected("{ a: 1 ]"),
+            (
+                "expected ',' or '}' after map entry, found ']'".into(),
+                1,
+                8
+            )
+        );
+        assert_eq!(
+            unexpected("{ 1: 2 }"),
+            ("expected map key or '}', found integer 1".into(), 1, 3)
+        );
+        assert_eq!(
+            unexpected("{ a: }"),
+            ("expected value, found '}'".into(), 1, 6)
+        );
+        assert_eq!(
+            unexpected("config"),
+            (
+                "expected '{' after `config`, found end of input".into(),
+                1,
+                7
+            )
+        );
+        assert_eq!(
+            unexpected(r#"1 "x""#),
+            (r#"expected end of input, found string "x""#.into(), 1, 3)
+        );
+    }
+
+    #[test]
+    fn unexpected_token_is_structured() {
+        assert_eq!(
+            parse("{ retries 3 }").unwrap_err().kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::Colon {
+                    key: "retries".into()
+                },
+                found: TokenKind::Int(3),
+            }
+        );
+        assert_eq!(
+            parse("[1,").unwrap_err().kind,
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::Value,
+                found: TokenKind::EOF,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_display_includes_position() {
+        let err = parse("{ a: null true }").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected ',' or '}' after map entry, found `true` at line 1, column 11"
+        );
+    }
+
+    fn recover(input: &str) -> (Option<Value>, Vec<(String, usize)>) {
+        let recovered = parse_recovering(input);
+        let errors = recovered
+            .errors
+            .iter()
+            .map(|err| (err.kind.to_string(), err.span.line))
+            .collect();
+
+        (recovered.value, errors)
+    }
+
+    #[test]
+    fn recovering_valid_input_has_no_errors() {
+        let input = "config {\n  a: 1\n  b: [1, 2]\n}";
+        let recovered = parse_recovering(input);
+
+        assert!(recovered.errors.is_empty());
+        assert_eq!(recovered.value, Some(parse(input).unwrap()));
+    }
+
+    #[test]
+    fn recovering_collects_all_errors_in_map() {
+        let input = r#"
+            {
+                retries 3
+                timeout: 30
+                "name": "x\qy"
+                ports: [80, @, 443 8080]
+                mode: on
+                level: 2
+            }
+        "#;
+
+        let (value, errors) = recover(input);
+
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "expected ':' after key `retries`, found integer 3".into(),
+                    3
+                ),
+                (r"invalid escape `\q` in string".into(), 5),
+                ("invalid character '@'".into(), 6),
+                ("expected value, found ','".into(), 6),
+                (
+                    "expected ',' or ']' after list element, found integer 8080".into(),
+                    6
+                ),
+                (
+                    "expected '{' after `on`, found identifier `level`".into(),
+                    8
+                ),
+            ]
+        );
+
+        use std::collections::BTreeMap;
+
+        // failed entries are dropped, bad literals become placeholders
+        let mut map = BTreeMap::new();
+        map.insert("timeout".into(), Value::Int(30));
+        map.insert("name".into(), Value::String("".into()));
+        map.insert(
+            "ports".into(),
+            Value::List(vec![Value::Int(80), Value::Int(443)]),
+        );
+
+        assert_eq!(value, Some(Value::Map(map)));
+    }
+
+    #[test]
+    fn recovering_reports_unclosed_containers_once() {
+        let (value, errors) = recover("{ a: [1, 2\n");
+
+        assert_eq!(
+            errors,
+            vec![(
+                "expected ',' or ']' after list element, found end of input".into(),
+                2
+            )]
+        );
+
+        use std::collections::BTreeMap;
+
+        let mut map = BTreeMap::new();
+        map.insert("a".into(), Value::List(vec![Value::Int(1), Value::Int(2)]));
+        assert_eq!(value, Some(Value::Map(map)));
+    }
+
+    #[test]
+    fn recovering_handles_mismatched_closers() {
+        let (value, errors) = recover("[1, 2 } , 3]");
+
+        assert_eq!(
+            errors,
+            vec![(
+                "expected ',' or ']' after list element, found '}'".into(),
+                1
+            )]
+        );
+        assert_eq!(value, Some(Value::List(vec![Value::Int(1), Value::Int(2)])));
+    }
+
+    #[test]
+    fn recovering_skips_backslash_before_line_break() {
+        let (value, errors) = recover("{ a: \"abc\\\n b: 1 }");
+
+        assert_eq!(errors, vec![("unterminated string".into(), 1)]);
+
+        use std::collections::BTreeMap;
+
+        let mut map = BTreeMap::new();
+        map.insert("a".into(), Value::String("".into()));
+        map.insert("b".into(), Value::Int(1));
+        assert_eq!(value, Some(Value::Map(map)));
+    }
+
+    #[test]
+    fn recovering_without_any_value() {
+        let (value, errors) = recover("@ :");
+
+        assert_eq!(value, None);
+        assert_eq!(
+            errors,
+            vec![
+                ("invalid character '@'".into(), 1),
+                ("expected value, found ':'".into(), 1),
+            ]
+        );
+    }
+
+    proptest! {
+        // bad literals and stray characters mixed into valid syntax
+        #[test]
+        fn recovering_agrees_with_parse(
+            source in r#"([{}\[\],: \n]|a|"x"|"\\q|"é|0x|0xz|0x1|b64"=|-|99999999999999999999|# c\n|\t|@){0,24}"#
+        ) {
+            let recovered = parse_recovering(&source);
+
+            match parse(&source) {
+                Ok(value) => {
+                    prop_assert_eq!(recovered.value, Some(value));
+                    prop_assert!(recovered.errors.is_empty());
+                }
+                Err(err) => prop_assert_eq!(recovered.errors.first(), Some(&err)),
+            }
+        }
+    }
 }
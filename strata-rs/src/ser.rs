@@ -0,0 +1,632 @@
+use std::ops::Range;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::decode::{DecodeLimits, decode_with_limits};
+use crate::encode::{encode_sleb128, encode_uleb128};
+use crate::error::{EncodeError, SerdeError};
+use crate::value::Value;
+
+/// Serialize `value` to canonical Strata Core Binary.
+///
+/// Structs and maps become maps, whose entries are written in canonical
+/// key order, sequences and tuples become lists, and integers must fit in
+/// i64. Only `serialize_bytes` produces Bytes, so a `Vec<u8>` is a List;
+/// use `serde_bytes` for byte buffers.
+///
+/// The bytes are written as the value is walked, without building a
+/// `Value` first. Only map entries are buffered, to put them in order.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerdeError> {
+    let mut out = Vec::new();
+    value.serialize(Serializer { out: &mut out })?;
+    Ok(out)
+}
+
+/// Serialize `value` to a `Value` with the same mapping as `to_vec`.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, SerdeError> {
+    // any depth `to_vec` can write is read back
+    Ok(decode_with_limits(
+        &to_vec(value)?,
+        DecodeLimits::unlimited(),
+    )?)
+}
+
+struct Serializer<'a> {
+    out: &'a mut Vec<u8>,
+}
+
+fn int<T: TryInto<i64> + ToString + Copy>(number: T, out: &mut Vec<u8>) -> Result<(), SerdeError> {
+    let number = number
+        .try_into()
+        .map_err(|_| SerdeError::IntegerOutOfRange(number.to_string()))?;
+    write_int(number, out);
+    Ok(())
+}
+
+fn write_int(number: i64, out: &mut Vec<u8>) {
+    out.push(0x10);
+    encode_sleb128(number, out);
+}
+
+fn write_str(string: &str, out: &mut Vec<u8>) {
+    out.push(0x20);
+    encode_uleb128(string.len() as u64, out);
+    out.extend_from_slice(string.as_bytes());
+}
+
+fn write_header(tag: u8, len: usize, out: &mut Vec<u8>) {
+    out.push(tag);
+    encode_uleb128(len as u64, out);
+}
+
+// other variants are a map from their name to their content
+fn write_variant(name: &str, out: &mut Vec<u8>) {
+    write_header(0x40, 1, out);
+    write_str(name, out);
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    type SerializeSeq = SerializeList<'a>;
+    type SerializeTuple = SerializeList<'a>;
+    type SerializeTupleStruct = SerializeList<'a>;
+    type SerializeTupleVariant = SerializeList<'a>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeMap<'a>;
+    type SerializeStructVariant = SerializeMap<'a>;
+
+    // Strata is a binary format, so types like `Value` write their
+    // compact forms
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerdeError> {
+        self.out.push(if v { 0x02 } else { 0x01 });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerdeError> {
+        write_int(v, self.out);
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerdeError> {
+        int(v, self.out)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerdeError> {
+        write_int(v.into(), self.out);
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerdeError> {
+        int(v, self.out)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerdeError> {
+        int(v, self.out)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), SerdeError> {
+        Err(SerdeError::UnsupportedType("f32"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), SerdeError> {
+        Err(SerdeError::UnsupportedType("f64"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerdeError> {
+        write_str(v.encode_utf8(&mut [0; 4]), self.out);
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerdeError> {
+        write_str(v, self.out);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerdeError> {
+        write_header(0x21, v.len(), self.out);
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SerdeError> {
+        self.out.push(0x00);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerdeError> {
+        self.out.push(0x00);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), SerdeError> {
+        self.out.push(0x00);
+        Ok(())
+    }
+
+    /// Unit variants are their name, e.g. `"Active"`.
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), SerdeError> {
+        write_str(variant, self.out);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        value.serialize(self)
+    }
+
+    /// Other variants are a map from their name to their content, e.g.
+    /// `{ Moved: { x: 1, y: 2 } }`.
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        write_variant(name, self.out);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList<'a>, SerdeError> {
+        Ok(SerializeList::new(self.out, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList<'a>, SerdeError> {
+        Ok(SerializeList::new(self.out, Some(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SerializeList<'a>, SerdeError> {
+        Ok(SerializeList::new(self.out, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList<'a>, SerdeError> {
+        write_variant(name, self.out);
+        Ok(SerializeList::new(self.out, Some(len)))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<SerializeMap<'a>, SerdeError> {
+        Ok(SerializeMap::new(self.out))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<SerializeMap<'a>, SerdeError> {
+        Ok(SerializeMap::new(self.out))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        name: &'static str,
+        _: usize,
+    ) -> Result<SerializeMap<'a>, SerdeError> {
+        write_variant(name, self.out);
+        Ok(SerializeMap::new(self.out))
+    }
+}
+
+struct SerializeList<'a> {
+    out: &'a mut Vec<u8>,
+    // A list whose length is known up front is written straight to
+    // `out`; otherwise its items wait here until they are counted.
+    pending: Option<Vec<u8>>,
+    len: Option<usize>,
+    count: usize,
+}
+
+impl<'a> SerializeList<'a> {
+    fn new(out: &'a mut Vec<u8>, len: Option<usize>) -> Self {
+        let pending = match len {
+            Some(len) => {
+                write_header(0x30, len, out);
+                None
+            }
+            None => Some(Vec::new()),
+        };
+
+        Self {
+            out,
+            pending,
+            len,
+            count: 0,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let out = match &mut self.pending {
+            Some(pending) => pending,
+            None => &mut *self.out,
+        };
+        value.serialize(Serializer { out })?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), SerdeError> {
+        if let Some(pending) = self.pending {
+            write_header(0x30, self.count, self.out);
+            self.out.extend_from_slice(&pending);
+        } else if self.len != Some(self.count) {
+            return Err(ser::Error::custom(format!(
+                "sequence declared {} elements but wrote {}",
+                self.len.unwrap_or(0),
+                self.count
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeList<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+/// Entries arrive in any order but must be written in canonical order, so
+/// each value is encoded into `values` and the entries are sorted at the
+/// end.
+struct SerializeMap<'a> {
+    out: &'a mut Vec<u8>,
+    values: Vec<u8>,
+    entries: Vec<(String, Range<usize>)>,
+    next_key: Option<String>,
+}
+
+impl<'a> SerializeMap<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            values: Vec::new(),
+            entries: Vec::new(),
+            next_key: None,
+        }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), SerdeError> {
+        let start = self.values.len();
+        value.serialize(Serializer {
+            out: &mut self.values,
+        })?;
+        self.entries.push((key, start..self.values.len()));
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), SerdeError> {
+        // BTreeMap's order: bytewise on the UTF-8 keys
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if self.entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(EncodeError::DuplicateKey.into());
+        }
+
+        write_header(0x40, self.entries.len(), self.out);
+        for (key, range) in &self.entries {
+            write_str(key, self.out);
+            self.out.extend_from_slice(&self.values[range.clone()]);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeMap<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .next_key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+/// Map keys must be strings. Chars and unit variants, which serialize as
+/// strings everywhere else, are accepted too.
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerdeError;
+
+    type SerializeSeq = Impossible<String, SerdeError>;
+    type SerializeTuple = Impossible<String, SerdeError>;
+    type SerializeTupleStruct = Impossible<String, SerdeError>;
+    type SerializeTupleVariant = Impossible<String, SerdeError>;
+    type SerializeMap = Impossible<String, SerdeError>;
+    type SerializeStruct = Impossible<String, SerdeError>;
+    type SerializeStructVariant = Impossible<String, SerdeError>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, SerdeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_none(self) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_unit(self) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Err(SerdeError::KeyMustBeString)
+    }
+}
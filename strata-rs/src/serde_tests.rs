@@ -0,0 +1,453 @@
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use proptest::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use serde_bytes::ByteBuf;
+
+    use crate::decode::{DecodeLimits, DecodeOptions, decode_with_options};
+    use crate::encode::encode;
+    use crate::error::{DecodeErrorKind, EncodeError, SerdeError};
+    use crate::test_strategies::arb_value;
+    use crate::value::Value;
+    use crate::{bytes, from_slice, from_value, int, list, map, null, string, to_value, to_vec};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Service {
+        // declared out of canonical order on purpose
+        retries: u8,
+        name: String,
+        ports: Vec<u16>,
+        #[serde(with = "serde_bytes")]
+        key: Vec<u8>,
+        timeout: Option<u32>,
+        mode: Mode,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Active,
+        Limited(u32),
+        Window { from: i64, to: i64 },
+        Pair(bool, bool),
+    }
+
+    fn service() -> Service {
+        Service {
+            retries: 3,
+            name: "api".to_string(),
+            ports: vec![80, 443],
+            key: vec![0xde, 0xad],
+            timeout: None,
+            mode: Mode::Active,
+        }
+    }
+
+    #[test]
+    fn struct_encodes_as_canonical_map() {
+        let expected = map! {
+            "key" => bytes!(vec![0xde, 0xad]),
+            "mode" => string!("Active"),
+            "name" => string!("api"),
+            "ports" => list![int!(80), int!(443)],
+            "retries" => int!(3),
+            "timeout" => null!(),
+        };
+
+        assert_eq!(to_value(&service()).unwrap(), expected);
+        assert_eq!(to_vec(&service()).unwrap(), encode(&expected).unwrap());
+        assert_eq!(
+            from_slice::<Service>(&to_vec(&service()).unwrap()).unwrap(),
+            service()
+        );
+    }
+
+    #[test]
+    fn enum_variants_roundtrip() {
+        for (mode, value) in [
+            (Mode::Active, string!("Active")),
+            (Mode::Limited(5), map! { "Limited" => int!(5) }),
+            (
+                Mode::Window { from: -1, to: 1 },
+                map! { "Window" => map! { "from" => int!(-1), "to" => int!(1) } },
+            ),
+            (
+                Mode::Pair(true, false),
+                map! { "Pair" => list![Value::Bool(true), Value::Bool(false)] },
+            ),
+        ] {
+            assert_eq!(to_value(&mode).unwrap(), value);
+            assert_eq!(from_slice::<Mode>(&to_vec(&mode).unwrap()).unwrap(), mode);
+        }
+    }
+
+    #[test]
+    fn only_serde_bytes_are_bytes() {
+        assert_eq!(
+            to_value(&ByteBuf::from(vec![1, 2])).unwrap(),
+            bytes!(vec![1, 2])
+        );
+        assert_eq!(to_value(&ByteBuf::new()).unwrap(), bytes!(vec![]));
+
+        // sequences are lists whatever their element type
+        assert_eq!(to_value(&vec![1u8, 2]).unwrap(), list![int!(1), int!(2)]);
+        assert_eq!(to_value(&[7u8; 2]).unwrap(), list![int!(7), int!(7)]);
+        assert_eq!(to_value(&Vec::<u8>::new()).unwrap(), list![]);
+
+        // the same as `From<Vec<u8>> for Value`
+        assert_eq!(to_value(&vec![1u8, 2]).unwrap(), Value::from(vec![1u8, 2]));
+
+        // bytes and lists of small integers read back either way
+        assert_eq!(
+            from_value::<Vec<u8>>(bytes!(vec![1, 2])).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(from_value::<[u8; 2]>(bytes!(vec![1, 2])).unwrap(), [1, 2]);
+        assert_eq!(
+            from_value::<Vec<u8>>(list![int!(1), int!(2)]).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            from_value::<ByteBuf>(bytes!(vec![1, 2])).unwrap(),
+            ByteBuf::from(vec![1, 2])
+        );
+
+        // whatever the sequence's element type
+        assert_eq!(
+            from_value::<Vec<i64>>(bytes!(vec![1, 2])).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(from_value::<(u8, u16)>(bytes!(vec![1, 2])).unwrap(), (1, 2));
+    }
+
+    #[test]
+    fn sequences_of_unknown_length_are_counted() {
+        struct Evens(u32);
+
+        impl Serialize for Evens {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq((0..self.0).filter(|n| n % 2 == 0))
+            }
+        }
+
+        assert_eq!(
+            to_vec(&Evens(5)).unwrap(),
+            encode(&list![int!(0), int!(2), int!(4)]).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_slice_requires_canonical_input() {
+        // { b: 1, a: 2 } with its keys out of order
+        let unsorted = [
+            0x40, 0x02, 0x20, 0x01, b'b', 0x10, 0x01, 0x20, 0x01, b'a', 0x10, 0x02,
+        ];
+        let err = from_slice::<BTreeMap<String, i64>>(&unsorted).unwrap_err();
+        assert_eq!(err.code(), "decode.unsorted_map_keys");
+
+        // 1 as a two-byte varint
+        let err = from_slice::<i64>(&[0x10, 0x81, 0x00]).unwrap_err();
+        assert_eq!(err.code(), "decode.non_canonical_varint");
+    }
+
+    #[test]
+    fn from_slice_limits_depth() {
+        let mut deep = null!();
+        for _ in 0..DecodeLimits::DEFAULT_MAX_DEPTH + 1 {
+            deep = list![deep];
+        }
+        let bytes = to_vec(&deep).unwrap();
+
+        let err = from_slice::<Value>(&bytes).unwrap_err();
+        assert_eq!(err.code(), "decode.depth_limit_exceeded");
+
+        let options = DecodeOptions {
+            canonical: true,
+            limits: DecodeLimits::unlimited(),
+        };
+        let value = decode_with_options(&bytes, options).unwrap();
+        assert_eq!(from_value::<Value>(value).unwrap(), deep);
+    }
+
+    #[test]
+    fn maps_need_string_keys() {
+        let mut counts = HashMap::new();
+        counts.insert("b".to_string(), 2);
+        counts.insert("a".to_string(), 1);
+        assert_eq!(
+            to_value(&counts).unwrap(),
+            map! { "a" => int!(1), "b" => int!(2) }
+        );
+
+        #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        enum Region {
+            Eu,
+        }
+
+        // unit variants are strings, so they work as keys
+        let by_region = BTreeMap::from([(Region::Eu, 1)]);
+        assert_eq!(to_value(&by_region).unwrap(), map! { "Eu" => int!(1) });
+
+        let numbered = BTreeMap::from([(1, "one")]);
+        assert_eq!(to_value(&numbered), Err(SerdeError::KeyMustBeString));
+    }
+
+    #[test]
+    fn unrepresentable_values_are_rejected() {
+        assert_eq!(
+            to_vec(&u64::MAX),
+            Err(SerdeError::IntegerOutOfRange(
+                "18446744073709551615".to_string()
+            ))
+        );
+        assert_eq!(to_value(&(i64::MAX as u64)).unwrap(), int!(i64::MAX));
+        assert_eq!(to_vec(&1.5f64), Err(SerdeError::UnsupportedType("f64")));
+
+        #[derive(Serialize)]
+        struct Clash {
+            a: i64,
+            #[serde(flatten)]
+            rest: BTreeMap<String, i64>,
+        }
+
+        let clash = Clash {
+            a: 1,
+            rest: BTreeMap::from([("a".to_string(), 2)]),
+        };
+        assert_eq!(
+            to_vec(&clash),
+            Err(SerdeError::Encode(EncodeError::DuplicateKey))
+        );
+        assert_eq!(
+            to_vec(&1.5f64).unwrap_err().to_string(),
+            "f64 is not a Strata type"
+        );
+    }
+
+    #[test]
+    fn decoding_is_strict() {
+        let err = from_value::<u8>(int!(300)).unwrap_err();
+        assert_eq!(err.to_string(), "invalid value: integer `300`, expected u8");
+        assert_eq!(err.code(), "serde.message");
+
+        let err = from_value::<u16>(string!("80")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid type: string \"80\", expected u16");
+
+        let err = from_value::<(i64, i64)>(list![int!(1), int!(2), int!(3)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid length 3, expected 2 elements in sequence"
+        );
+
+        let mut value = to_value(&service()).unwrap();
+        let Value::Map(map) = &mut value else {
+            unreachable!()
+        };
+        map.remove("name");
+        assert_eq!(
+            from_value::<Service>(value).unwrap_err().to_string(),
+            "missing field `name`"
+        );
+
+        let err = from_value::<Mode>(string!("Unknown")).unwrap_err();
+        assert!(err.to_string().starts_with("unknown variant `Unknown`"));
+
+        let err =
+            from_value::<Mode>(map! { "Active" => null!(), "Limited" => int!(1) }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid type: map, expected a variant name or a map with one entry"
+        );
+
+        assert_eq!(
+            from_value::<f64>(int!(1)),
+            Err(SerdeError::UnsupportedType("f64"))
+        );
+    }
+
+    #[test]
+    fn decode_errors_pass_through() {
+        let err = from_slice::<u8>(&[0x99]).unwrap_err();
+
+        let SerdeError::Decode(decode) = &err else {
+            panic!("expected decode error, got {:?}", err);
+        };
+        assert_eq!(decode.kind, DecodeErrorKind::InvalidTag(0x99));
+        assert_eq!(err.code(), "decode.invalid_tag");
+    }
+
+    #[test]
+    fn optional_fields() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Limits {
+            burst: Option<u32>,
+            rate: Option<u32>,
+        }
+
+        // absent and null both read as None
+        let limits: Limits = from_value(map! { "burst" => null!() }).unwrap();
+        assert_eq!(
+            limits,
+            Limits {
+                burst: None,
+                rate: None
+            }
+        );
+    }
+
+    #[test]
+    fn value_maps_onto_json() {
+        let value = map! {
+            "id" => int!(-7),
+            "key" => bytes!(vec![0xde, 0xad, 0xbe, 0xef]),
+            "name" => string!("api"),
+            "tags" => list![Value::Bool(true), null!()],
+        };
+        let json = r#"{"id":-7,"key":{"$bytes":"3q2+7w=="},"name":"api","tags":[true,null]}"#;
+
+        assert_eq!(serde_json::to_string(&value).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Value>(json).unwrap(), value);
+    }
+
+    #[test]
+    fn dollar_keys_roundtrip_through_json() {
+        for value in [
+            map! { "$bytes" => string!("aGk=") },
+            map! { "$bytes" => string!("not base64!") },
+            map! { "$$bytes" => bytes!(vec![1]) },
+            map! { "$" => int!(1), "$ref" => map! { "$bytes" => null!() } },
+        ] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        }
+
+        assert_eq!(
+            serde_json::to_string(&map! { "$bytes" => string!("aGk=") }).unwrap(),
+            r#"{"$$bytes":"aGk="}"#
+        );
+
+        // the Strata format has native bytes, so keys are written as they are
+        let value = map! { "$bytes" => string!("aGk=") };
+        assert_eq!(to_vec(&value).unwrap(), encode(&value).unwrap());
+    }
+
+    #[test]
+    fn json_integers_must_fit_i64() {
+        assert_eq!(
+            serde_json::from_str::<Value>("9223372036854775807").unwrap(),
+            int!(i64::MAX)
+        );
+
+        let err = serde_json::from_str::<Value>("9223372036854775808").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value: integer `9223372036854775808`, expected an integer in i64 range at line 1 column 19"
+        );
+
+        let err = serde_json::from_str::<Value>("1.0").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid type: floating point `1.0`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn json_maps_are_strict() {
+        let err = serde_json::from_str::<Value>(r#"{"a":1,"a":2}"#).unwrap_err();
+        assert!(
+            err.to_string().starts_with("duplicate map key \"a\""),
+            "{}",
+            err
+        );
+
+        let err = serde_json::from_str::<Value>(r#"{"$bytes":"not base64!"}"#).unwrap_err();
+        assert!(err.to_string().starts_with("invalid value"), "{}", err);
+
+        // every user key starting with `$` is written with another `$`
+        assert_eq!(
+            serde_json::from_str::<Value>(r#"{"$$bytes":"not base64!"}"#).unwrap(),
+            map! { "$bytes" => string!("not base64!") }
+        );
+
+        // only a lone string entry is tagged bytes
+        assert_eq!(
+            serde_json::from_str::<Value>(r#"{"$bytes":1}"#).unwrap(),
+            map! { "$bytes" => int!(1) }
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(r#"{"$bytes":"","x":1}"#).unwrap(),
+            map! { "$bytes" => string!(""), "x" => int!(1) }
+        );
+    }
+
+    #[test]
+    fn value_embeds_in_structs() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Envelope {
+            kind: String,
+            payload: Value,
+        }
+
+        let envelope = Envelope {
+            kind: "config".to_string(),
+            payload: map! { "blob" => bytes!(vec![1, 2]) },
+        };
+
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"config","payload":{"blob":{"$bytes":"AQI="}}}"#
+        );
+        assert_eq!(serde_json::from_str::<Envelope>(&json).unwrap(), envelope);
+
+        // the Strata format carries bytes natively
+        assert_eq!(
+            to_value(&envelope).unwrap(),
+            map! {
+                "kind" => string!("config"),
+                "payload" => map! { "blob" => bytes!(vec![1, 2]) },
+            }
+        );
+        assert_eq!(
+            from_slice::<Envelope>(&to_vec(&envelope).unwrap()).unwrap(),
+            envelope
+        );
+    }
+
+    // maps keyed like the `$bytes` tag and its escapes
+    fn arb_dollar_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            "[A-Za-z0-9+/=! ]{0,8}".prop_map(Value::String),
+            prop::collection::vec(any::<u8>(), 0..4).prop_map(Value::Bytes),
+        ];
+
+        leaf.prop_recursive(3, 32, 4, |inner| {
+            prop::collection::btree_map(r"\$*(bytes|[a-z]?)", inner, 0..4).prop_map(Value::Map)
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn dollar_keys_never_read_as_tags(value in arb_dollar_value()) {
+            let json = serde_json::to_string(&value).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        }
+
+        #[test]
+        fn value_roundtrips_through_json(value in arb_value()) {
+            let json = serde_json::to_string(&value).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+        }
+
+        #[test]
+        fn value_is_unchanged_by_strata_serde(value in arb_value()) {
+            prop_assert_eq!(to_value(&value).unwrap(), value.clone());
+            prop_assert_eq!(to_vec(&value).unwrap(), encode(&value).unwrap());
+            prop_assert_eq!(from_value::<Value>(value.clone()).unwrap(), value);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use crate::cst::{self, Node, Segment};
+use crate::error::{ParseError, SpanRange};
+use crate::lexer::TokenKind;
+use crate::value::Value;
+
+/// A parsed value that remembers where in the source it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedValue {
+    pub range: SpanRange,
+    pub node: SpannedNode,
+}
+
+/// Mirrors `Value`, with spanned children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpannedNode {
+    Null,
+    Bool(bool),
+    Int(i64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<SpannedValue>),
+    Map(BTreeMap<String, SpannedEntry>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedEntry {
+    /// The key as written, including quotes.
+    pub key: SpanRange,
+    pub value: SpannedValue,
+}
+
+/// Parse Strata Text, keeping the source range of every value.
+///
+/// Accepts the same input as [`crate::parser::parse`], and `to_value()`
+/// on the result equals what `parse` returns. For duplicate keys the
+/// spans point at the entry that wins.
+pub fn parse_with_spans(input: &str) -> Result<SpannedValue, ParseError> {
+    Ok(from_node(&cst::parse(input)?.root))
+}
+
+fn from_node(node: &Node) -> SpannedValue {
+    let node_kind = match node {
+        Node::Scalar(token) => match &token.kind {
+            TokenKind::Null => SpannedNode::Null,
+            TokenKind::True => SpannedNode::Bool(true),
+            TokenKind::False => SpannedNode::Bool(false),
+            TokenKind::Int(number) => SpannedNode::Int(*number),
+            TokenKind::String(string) => SpannedNode::String(string.clone()),
+            TokenKind::Bytes(bytes) => SpannedNode::Bytes(bytes.clone()),
+            _ => unreachable!("scalar nodes hold literal tokens"),
+        },
+
+        Node::List(list) => SpannedNode::List(
+            list.items
+                .iter()
+                .map(|item| from_node(&item.value))
+                .collect(),
+        ),
+
+        Node::Map(map) => SpannedNode::Map(
+            map.entries
+                .iter()
+                .map(|entry| {
+                    let spanned = SpannedEntry {
+                        key: entry.key.range(),
+                        value: from_node(&entry.value),
+                    };
+                    (entry.key().to_string(), spanned)
+                })
+                .collect(),
+        ),
+    };
+
+    SpannedValue {
+        range: node.range(),
+        node: node_kind,
+    }
+}
+
+impl SpannedValue {
+    pub fn to_value(&self) -> Value {
+        match &self.node {
+            SpannedNode::Null => Value::Null,
+            SpannedNode::Bool(b) => Value::Bool(*b),
+            SpannedNode::Int(number) => Value::Int(*number),
+            SpannedNode::String(string) => Value::String(string.clone()),
+            SpannedNode::Bytes(bytes) => Value::Bytes(bytes.clone()),
+            SpannedNode::List(items) => Value::List(items.iter().map(Self::to_value).collect()),
+            SpannedNode::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(key, entry)| (key.clone(), entry.value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn get(&self, path: &[Segment]) -> Option<&SpannedValue> {
+        let Some((step, rest)) = path.split_first() else {
+            return Some(self);
+        };
+
+        match (&self.node, step) {
+            (SpannedNode::Map(map), Segment::Key(key)) => map.get(*key)?.value.get(rest),
+            (SpannedNode::List(items), Segment::Index(index)) => items.get(*index)?.get(rest),
+            _ => None,
+        }
+    }
+
+    /// Where the value at `path` is written, e.g. to report that a
+    /// validation rejected it.
+    pub fn span_of(&self, path: &[Segment]) -> Option<SpanRange> {
+        self.get(path).map(|value| value.range)
+    }
+}
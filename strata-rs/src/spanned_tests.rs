@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::cst::Segment;
+    use crate::error::{Span, SpanRange};
+    use crate::format::format_value;
+    use crate::parser::parse;
+    use crate::spanned::{SpannedNode, parse_with_spans};
+    use crate::test_strategies::arb_value;
+
+    const CONFIG: &str = "# limits\nserver {\n  port: 8080\n  \"tags\": [\"a\", 0xff]\n  tls { cert: \"é.pem\" }\n}\n";
+
+    fn range(start: (usize, usize, usize), end: (usize, usize, usize)) -> SpanRange {
+        SpanRange {
+            start: Span {
+                offset: start.0,
+                line: start.1,
+                column: start.2,
+            },
+            end: Span {
+                offset: end.0,
+                line: end.1,
+                column: end.2,
+            },
+        }
+    }
+
+    #[test]
+    fn spans_cover_each_value() {
+        let spanned = parse_with_spans(CONFIG).unwrap();
+
+        assert_eq!(spanned.to_value(), parse(CONFIG).unwrap());
+
+        // the shorthand root runs from its key to the closing brace
+        assert_eq!(spanned.range, range((9, 2, 1), (79, 6, 2)));
+
+        assert_eq!(
+            spanned.span_of(&["server".into(), "port".into()]),
+            Some(range((26, 3, 9), (30, 3, 13)))
+        );
+        assert_eq!(
+            spanned.span_of(&["server".into(), "tags".into()]),
+            Some(range((41, 4, 11), (52, 4, 22)))
+        );
+        assert_eq!(
+            spanned.span_of(&["server".into(), "tags".into(), 1.into()]),
+            Some(range((47, 4, 17), (51, 4, 21)))
+        );
+
+        // columns count characters, offsets count bytes
+        assert_eq!(
+            spanned.span_of(&[
+                Segment::Key("server"),
+                Segment::Key("tls"),
+                Segment::Key("cert"),
+            ]),
+            Some(range((67, 5, 15), (75, 5, 22)))
+        );
+    }
+
+    #[test]
+    fn key_spans_include_quotes() {
+        let spanned = parse_with_spans(CONFIG).unwrap();
+        let server = spanned.get(&["server".into()]).unwrap();
+
+        let SpannedNode::Map(entries) = &server.node else {
+            panic!("server is a map");
+        };
+        assert_eq!(entries["tags"].key, range((33, 4, 3), (39, 4, 9)));
+    }
+
+    #[test]
+    fn duplicate_keys_point_at_the_winner() {
+        let spanned = parse_with_spans("{ a: 1, a: 2 }").unwrap();
+
+        assert_eq!(
+            spanned.span_of(&["a".into()]),
+            Some(range((11, 1, 12), (12, 1, 13)))
+        );
+    }
+
+    #[test]
+    fn missing_paths_and_errors() {
+        let spanned = parse_with_spans("[1, {a: 2}]").unwrap();
+
+        assert!(spanned.span_of(&[2.into()]).is_none());
+        assert!(spanned.span_of(&["a".into()]).is_none());
+        assert!(spanned.span_of(&[1.into(), "b".into()]).is_none());
+
+        assert_eq!(
+            parse_with_spans("{ a 1 }").unwrap_err(),
+            parse("{ a 1 }").unwrap_err()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn spanned_values_match_parse(value in arb_value()) {
+            let text = format_value(&value);
+            let spanned = parse_with_spans(&text).unwrap();
+
+            prop_assert_eq!(spanned.to_value(), value);
+            prop_assert_eq!(spanned.range.start.offset, 0);
+            prop_assert_eq!(spanned.range.end.offset, text.len() - 1);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{self, Read};
+
+use crate::decode::{DecodeOptions, Decoder, Source};
+use crate::error::{DecodeError, DecodeErrorKind, StrataError};
+use crate::value::Value;
+
+/// Incremental decoder over any `std::io::Read`.
+///
+/// Error offsets count bytes from the start of the stream, so they match
+/// what `decode` reports for the same bytes held in memory. Payloads are
+/// read as they arrive; a hostile length prefix cannot force a large
+/// allocation up front.
+///
+/// Reads are unbuffered: wrap files and sockets in a `BufReader`.
+pub struct StreamDecoder<R> {
+    decoder: Decoder<ReadSource<R>>,
+    multi_value: bool,
+    done: bool,
+}
+
+/// Bytes from a reader, with one byte of lookahead to find value
+/// boundaries.
+struct ReadSource<R> {
+    reader: R,
+    offset: usize,
+    peeked: Option<u8>,
+}
+
+impl<R: Read> ReadSource<R> {
+    fn eof(&self) -> StrataError {
+        DecodeError {
+            kind: DecodeErrorKind::UnexpectedEOF,
+            offset: self.offset,
+        }
+        .into()
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        if self.peeked.is_none() {
+            let mut buf = [0u8; 1];
+            loop {
+                match self.reader.read(&mut buf) {
+                    Ok(0) => return Ok(None),
+                    Ok(_) => break,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            self.peeked = Some(buf[0]);
+        }
+        Ok(self.peeked)
+    }
+}
+
+impl<R: Read> Source for ReadSource<R> {
+    type Chunk = Vec<u8>;
+    type Error = StrataError;
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_byte(&mut self) -> Result<u8, StrataError> {
+        match self.peek()? {
+            Some(byte) => {
+                self.peeked = None;
+                self.offset += 1;
+                Ok(byte)
+            }
+            None => Err(self.eof()),
+        }
+    }
+
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<u8>, StrataError> {
+        let mut buf = Vec::new();
+        if len == 0 {
+            return Ok(buf);
+        }
+
+        if let Some(byte) = self.peeked.take() {
+            buf.push(byte);
+        }
+
+        // grows with the data actually received, not with `len`
+        let wanted = (len - buf.len()) as u64;
+        (&mut self.reader).take(wanted).read_to_end(&mut buf)?;
+
+        if buf.len() < len {
+            return Err(self.eof());
+        }
+
+        self.offset += len;
+        Ok(buf)
+    }
+
+    // the length of the stream is unknown, so lists grow as items arrive
+    fn capacity(&self, _: usize) -> usize {
+        0
+    }
+}
+
+impl<R: Read> StreamDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::default())
+    }
+
+    /// Limits apply to each value separately.
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
+        let source = ReadSource {
+            reader,
+            offset: 0,
+            peeked: None,
+        };
+
+        Self {
+            decoder: Decoder::new(source, options),
+            multi_value: false,
+            done: false,
+        }
+    }
+
+    /// By default the stream must hold exactly one value and anything after
+    /// it is `TrailingBytes`. In multi-value mode the stream is a sequence of
+    /// concatenated values ending at a value boundary.
+    pub fn multi_value(mut self, multi_value: bool) -> Self {
+        self.multi_value = multi_value;
+        self
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.decoder.source().offset()
+    }
+
+    /// Returns the next value, or `None` once the stream is exhausted.
+    pub fn next_value(&mut self) -> Result<Option<Value>, StrataError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.next_inner();
+        if !matches!(result, Ok(Some(_))) || !self.multi_value {
+            self.done = true;
+        }
+        result
+    }
+
+    fn next_inner(&mut self) -> Result<Option<Value>, StrataError> {
+        if !self.multi_value {
+            return self.decode_single().map(Some);
+        }
+
+        if self.decoder.source_mut().peek()?.is_none() {
+            return Ok(None);
+        }
+
+        self.decoder.reset_budget();
+        self.decoder.decode_tree().map(Some)
+    }
+
+    fn decode_single(&mut self) -> Result<Value, StrataError> {
+        self.decoder.reset_budget();
+        let value = self.decoder.decode_tree()?;
+
+        if self.decoder.source_mut().peek()?.is_some() {
+            return Err(self.decoder.err(DecodeErrorKind::TrailingBytes).into());
+        }
+
+        Ok(value)
+    }
+}
+
+impl<R: Read> Iterator for StreamDecoder<R> {
+    type Item = Result<Value, StrataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+/// Decode exactly one value from a reader.
+pub fn decode_reader<R: Read>(reader: R) -> Result<Value, StrataError> {
+    StreamDecoder::new(reader).decode_single()
+}
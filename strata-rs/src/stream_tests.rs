@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read};
+
+    use crate::decode::{DecodeLimits, DecodeOptions, decode, decode_with_options};
+    use crate::error::{DecodeErrorKind, StrataError};
+    use crate::stream::{StreamDecoder, decode_reader};
+    use crate::value::Value;
+
+    // hands out one byte per read call
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn decode_err(result: Result<Value, StrataError>) -> (DecodeErrorKind, usize) {
+        match result {
+            Err(StrataError::Decode(e)) => (e.kind, e.offset),
+            other => panic!("expected decode error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stream_single_value() {
+        // { "x": [true, null] }
+        let bytes = vec![0x40, 0x01, 0x20, 0x01, b'x', 0x30, 0x02, 0x02, 0x00];
+
+        let value = decode_reader(Trickle(&bytes)).unwrap();
+        assert_eq!(value, decode(&bytes).unwrap());
+    }
+
+    #[test]
+    fn stream_single_value_rejects_trailing_bytes() {
+        let err = decode_err(decode_reader(Cursor::new(vec![0x00, 0x00])));
+        assert_eq!(err, (DecodeErrorKind::TrailingBytes, 1));
+    }
+
+    #[test]
+    fn stream_errors_match_slice_decoder() {
+        for bytes in [
+            vec![],
+            vec![0xFF],
+            vec![0x20, 0x05, b'h'],
+            vec![0x20, 0x02, 0xff, 0xff],
+            vec![
+                0x10, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+            ],
+        ] {
+            let expected = decode(&bytes).unwrap_err();
+            let err = decode_err(decode_reader(Trickle(&bytes)));
+            assert_eq!(
+                err,
+                (expected.kind, expected.offset),
+                "input {:02x?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn stream_options_match_slice_decoder() {
+        let options = DecodeOptions {
+            canonical: true,
+            limits: DecodeLimits {
+                max_depth: 1,
+                max_length: 2,
+                ..DecodeLimits::default()
+            },
+        };
+
+        for bytes in [
+            // unsorted and duplicate map keys
+            vec![0x40, 0x02, 0x20, 0x01, b'b', 0x00, 0x20, 0x01, b'a', 0x00],
+            vec![0x40, 0x02, 0x20, 0x01, b'a', 0x00, 0x20, 0x01, b'a', 0x00],
+            // over-long length prefix
+            vec![0x20, 0x81, 0x00, b'h'],
+            // nested too deep, payload too long
+            vec![0x30, 0x01, 0x30, 0x00],
+            vec![0x21, 0x03, 0x01, 0x02, 0x03],
+        ] {
+            let expected = decode_with_options(&bytes, options).unwrap_err();
+            let mut decoder = StreamDecoder::with_options(Trickle(&bytes), options);
+            let err = decode_err(decoder.next_value().map(Option::unwrap));
+            assert_eq!(
+                err,
+                (expected.kind, expected.offset),
+                "input {:02x?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn stream_multi_value() {
+        let bytes = vec![0x10, 0x01, 0x20, 0x02, b'h', b'i', 0x00];
+
+        let values: Vec<_> = StreamDecoder::new(Trickle(&bytes))
+            .multi_value(true)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            values,
+            vec![Value::Int(1), Value::String("hi".into()), Value::Null]
+        );
+    }
+
+    #[test]
+    fn stream_multi_value_truncated_tail() {
+        let bytes = vec![0x10, 0x01, 0x20, 0x02, b'h'];
+        let mut decoder = StreamDecoder::new(Cursor::new(bytes)).multi_value(true);
+
+        assert_eq!(decoder.next_value().unwrap(), Some(Value::Int(1)));
+
+        let err = decode_err(decoder.next_value().map(Option::unwrap));
+        assert_eq!(err, (DecodeErrorKind::UnexpectedEOF, 4));
+
+        // fused after an error
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn stream_empty_input() {
+        let mut multi = StreamDecoder::new(Cursor::new(vec![])).multi_value(true);
+        assert_eq!(multi.next_value().unwrap(), None);
+
+        let err = decode_err(decode_reader(Cursor::new(vec![])));
+        assert_eq!(err, (DecodeErrorKind::UnexpectedEOF, 0));
+    }
+
+    #[test]
+    fn stream_huge_length_prefix() {
+        // bytes claiming 2^62 bytes of payload
+        let bytes = vec![
+            0x21, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x40, 0xaa,
+        ];
+
+        let err = decode_err(decode_reader(Cursor::new(bytes)));
+        assert_eq!(err, (DecodeErrorKind::UnexpectedEOF, 10));
+    }
+
+    #[test]
+    fn stream_canonical_options() {
+        let options = DecodeOptions {
+            canonical: true,
+            ..DecodeOptions::default()
+        };
+
+        // Int 1 as 0x81 0x00
+        let mut decoder = StreamDecoder::with_options(Cursor::new(vec![0x10, 0x81, 0x00]), options);
+        let err = decode_err(decoder.next_value().map(Option::unwrap));
+        assert_eq!(err, (DecodeErrorKind::NonCanonicalVarint, 1));
+    }
+
+    #[test]
+    fn stream_io_error() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+        }
+
+        assert!(matches!(decode_reader(Broken), Err(StrataError::Io(_))));
+    }
+}
@@ -0,0 +1,23 @@
+#![cfg(test)]
+
+use proptest::prelude::*;
+
+use crate::value::Value;
+
+/// Arbitrary `Value` trees for property tests.
+pub(crate) fn arb_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::Int),
+        any::<String>().prop_map(Value::String),
+        prop::collection::vec(any::<u8>(), 0..300).prop_map(Value::Bytes),
+    ];
+
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::List),
+            prop::collection::btree_map(any::<String>(), inner, 0..8).prop_map(Value::Map),
+        ]
+    })
+}
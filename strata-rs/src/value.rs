@@ -1,4 +1,6 @@
 use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Index, IndexMut};
 
 /// Core Strata value type.
 /// This is the in-memory representation used by encoders/decoders.
@@ -17,3 +19,421 @@ pub enum Value {
     List(Vec<Value>),
     Map(BTreeMap<String, Value>),
 }
+
+/// The variant of a `Value`, without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Null,
+    Bool,
+    Int,
+    String,
+    Bytes,
+    List,
+    Map,
+}
+
+impl ValueKind {
+    /// Lowercase name, as used in error messages.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ValueKind::Null => "null",
+            ValueKind::Bool => "bool",
+            ValueKind::Int => "int",
+            ValueKind::String => "string",
+            ValueKind::Bytes => "bytes",
+            ValueKind::List => "list",
+            ValueKind::Map => "map",
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Accessors return `None` when the value is another variant; nothing is
+/// coerced, so `as_int` on `String("1")` is `None`.
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Null => ValueKind::Null,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Int(_) => ValueKind::Int,
+            Value::String(_) => ValueKind::String,
+            Value::Bytes(_) => ValueKind::Bytes,
+            Value::List(_) => ValueKind::List,
+            Value::Map(_) => ValueKind::Map,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_mut(&mut self) -> Option<&mut i64> {
+        match self {
+            Value::Int(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut BTreeMap<String, Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The entry under `key`, if this is a Map that has one.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map()?.get(key)
+    }
+
+    /// The item at `index`, if this is a List that long.
+    pub fn get_index(&self, index: usize) -> Option<&Value> {
+        self.as_list()?.get(index)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_map_mut()?.get_mut(key)
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut Value> {
+        self.as_list_mut()?.get_mut(index)
+    }
+}
+
+/// `value["key"]`, panicking like `BTreeMap` indexing when `value` is not
+/// a Map or has no such key. Use `get` when either may happen.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Map(map) => map
+                .get(key)
+                .unwrap_or_else(|| panic!("no entry for key {:?}", key)),
+            other => panic!("cannot index {} with key {:?}", other.kind(), key),
+        }
+    }
+}
+
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self {
+            Value::Map(map) => map
+                .get_mut(key)
+                .unwrap_or_else(|| panic!("no entry for key {:?}", key)),
+            other => panic!("cannot index {} with key {:?}", other.kind(), key),
+        }
+    }
+}
+
+/// `value[i]`, panicking like slice indexing when `value` is not a List
+/// or is too short. Use `get_index` when either may happen.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            Value::List(items) => &items[index],
+            other => panic!("cannot index {} with {}", other.kind(), index),
+        }
+    }
+}
+
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        match self {
+            Value::List(items) => &mut items[index],
+            other => panic!("cannot index {} with {}", other.kind(), index),
+        }
+    }
+}
+
+/// Borrowed counterpart of `Value`, produced by `decode::decode_ref`.
+/// Strings and bytes point into the decoded input instead of being copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueRef<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    String(&'a str),
+    Bytes(&'a [u8]),
+    List(Vec<ValueRef<'a>>),
+    Map(BTreeMap<&'a str, ValueRef<'a>>),
+}
+
+impl ValueRef<'_> {
+    /// Copy into an owned `Value`.
+    pub fn to_owned(&self) -> Value {
+        match self {
+            ValueRef::Null => Value::Null,
+            ValueRef::Bool(b) => Value::Bool(*b),
+            ValueRef::Int(n) => Value::Int(*n),
+            ValueRef::String(s) => Value::String((*s).to_string()),
+            ValueRef::Bytes(b) => Value::Bytes(b.to_vec()),
+            ValueRef::List(items) => Value::List(items.iter().map(ValueRef::to_owned).collect()),
+            ValueRef::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(k, v)| ((*k).to_string(), v.to_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        value.to_owned()
+    }
+}
+
+/// Key that marks Bytes in human-readable serde formats.
+#[cfg(feature = "serde")]
+pub const BYTES_KEY: &str = "$bytes";
+
+/// `Value` maps onto the serde data model so it can be embedded in JSON,
+/// YAML, TOML or any other serde format:
+///
+/// - Null, Bool, String, List and Map use the format's own types
+/// - Int is an i64; formats that read numbers as floats (e.g. JavaScript
+///   JSON parsers) lose precision beyond 2^53
+/// - Bytes are `{ "$bytes": "<base64>" }` in human-readable formats and
+///   native bytes in binary ones
+/// - In human-readable formats, map keys starting with `$` get another `$`
+///   in front, so `"$bytes"` is written `"$$bytes"` and no key can be
+///   mistaken for the tag
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+        use serde::ser::SerializeMap;
+
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Int(number) => serializer.serialize_i64(*number),
+            Value::String(string) => serializer.serialize_str(string),
+            Value::Bytes(bytes) if serializer.is_human_readable() => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(BYTES_KEY, &STANDARD.encode(bytes))?;
+                map.end()
+            }
+            Value::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            Value::List(items) => serializer.collect_seq(items),
+            Value::Map(map) if serializer.is_human_readable() => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    if key.starts_with('$') {
+                        out.serialize_entry(&format!("${}", key), value)?;
+                    } else {
+                        out.serialize_entry(key, value)?;
+                    }
+                }
+                out.end()
+            }
+            Value::Map(map) => serializer.collect_map(map),
+        }
+    }
+}
+
+/// Reads what `Serialize` writes. Integers outside i64 and floats are
+/// rejected rather than rounded, and duplicate map keys are an error.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_any(ValueVisitor { human_readable })
+    }
+}
+
+#[cfg(feature = "serde")]
+struct ValueVisitor {
+    // only human-readable formats tag bytes
+    human_readable: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a Strata value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        serde::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Value, E> {
+        i64::try_from(v).map(Value::Int).map_err(|_| {
+            E::invalid_value(
+                serde::de::Unexpected::Unsigned(v),
+                &"an integer in i64 range",
+            )
+        })
+    }
+
+    fn visit_i128<E: serde::de::Error>(self, v: i128) -> Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("integer {} does not fit in i64", v)))
+    }
+
+    fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("integer {} does not fit in i64", v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::List(items))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD;
+        use serde::de::Error;
+
+        let mut map = BTreeMap::new();
+        // the "$bytes" tag was read, rather than an escaped "$$bytes" key
+        let mut tagged = false;
+
+        while let Some((mut key, value)) = access.next_entry::<String, Value>()? {
+            if self.human_readable && key.starts_with("$$") {
+                key.remove(0);
+            } else {
+                tagged |= self.human_readable && key == BYTES_KEY;
+            }
+            if map.contains_key(&key) {
+                return Err(A::Error::custom(format!("duplicate map key {:?}", key)));
+            }
+            map.insert(key, value);
+        }
+
+        // a lone "$bytes" string is tagged bytes
+        if tagged
+            && map.len() == 1
+            && let Some(Value::String(encoded)) = map.get(BYTES_KEY)
+        {
+            return STANDARD.decode(encoded).map(Value::Bytes).map_err(|_| {
+                A::Error::invalid_value(
+                    serde::de::Unexpected::Str(encoded),
+                    &"base64 in a \"$bytes\" entry",
+                )
+            });
+        }
+
+        Ok(Value::Map(map))
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::value::{Value, ValueKind};
+    use crate::{bytes, int, list, map, null, string};
+
+    fn service() -> Value {
+        map! {
+            "name" => string!("api"),
+            "ports" => list![int!(80), int!(443)],
+            "key" => bytes!(vec![0xde, 0xad]),
+            "timeout" => null!(),
+            "tls" => Value::Bool(true),
+        }
+    }
+
+    #[test]
+    fn kind_and_display() {
+        let kinds = [
+            (null!(), ValueKind::Null, "null"),
+            (Value::Bool(false), ValueKind::Bool, "bool"),
+            (int!(1), ValueKind::Int, "int"),
+            (string!(""), ValueKind::String, "string"),
+            (bytes!(vec![]), ValueKind::Bytes, "bytes"),
+            (list![], ValueKind::List, "list"),
+            (map! {}, ValueKind::Map, "map"),
+        ];
+
+        for (value, kind, name) in kinds {
+            assert_eq!(value.kind(), kind);
+            assert_eq!(kind.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn accessors_match_one_variant() {
+        let value = service();
+
+        assert_eq!(value["name"].as_str(), Some("api"));
+        assert_eq!(value["ports"].as_list().map(<[Value]>::len), Some(2));
+        assert_eq!(value["key"].as_bytes(), Some(&[0xde, 0xad][..]));
+        assert_eq!(value["tls"].as_bool(), Some(true));
+        assert!(value["timeout"].is_null());
+        assert_eq!(value.as_map().map(|map| map.len()), Some(5));
+
+        // no coercion between variants
+        assert_eq!(string!("1").as_int(), None);
+        assert_eq!(bytes!(b"api".to_vec()).as_str(), None);
+        assert_eq!(list![].as_bytes(), None);
+        assert!(!Value::Bool(false).is_null());
+    }
+
+    #[test]
+    fn navigation() {
+        let value = service();
+
+        assert_eq!(
+            value.get("ports").and_then(|p| p.get_index(1)),
+            Some(&int!(443))
+        );
+        assert_eq!(value["ports"][0].as_int(), Some(80));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(value.get_index(0), None);
+        assert_eq!(value["name"].get("x"), None);
+        assert_eq!(value["ports"].get_index(2), None);
+    }
+
+    #[test]
+    fn mutation() {
+        let mut value = service();
+
+        *value["ports"][1].as_int_mut().unwrap() = 8443;
+        value["ports"].as_list_mut().unwrap().push(int!(9000));
+        value
+            .get_mut("name")
+            .unwrap()
+            .as_string_mut()
+            .unwrap()
+            .push_str("-v2");
+        value["key"].as_bytes_mut().unwrap().clear();
+        *value.get_mut("tls").unwrap().as_bool_mut().unwrap() = false;
+        value
+            .as_map_mut()
+            .unwrap()
+            .insert("retries".to_string(), int!(3));
+        *value["ports"].get_index_mut(0).unwrap() = int!(8080);
+        value["timeout"] = int!(30);
+
+        assert_eq!(
+            value,
+            map! {
+                "name" => string!("api-v2"),
+                "ports" => list![int!(8080), int!(8443), int!(9000)],
+                "key" => bytes!(vec![]),
+                "retries" => int!(3),
+                "timeout" => int!(30),
+                "tls" => Value::Bool(false),
+            }
+        );
+
+        assert_eq!(int!(1).as_list_mut(), None);
+        assert_eq!(int!(1).get_mut("a"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry for key \"missing\"")]
+    fn index_missing_key_panics() {
+        let _ = &service()["missing"];
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot index string with key \"a\"")]
+    fn index_key_into_non_map_panics() {
+        let _ = &string!("api")["a"];
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot index map with 0")]
+    fn index_position_into_non_list_panics() {
+        let _ = &service()[0];
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_past_end_panics() {
+        let _ = &list![int!(1)][1];
+    }
+}
@@ -0,0 +1,123 @@
+use proc_macro2::Span;
+use syn::{Attribute, Field, LitStr, Result, Variant};
+
+/// `#[strata(...)]` on the struct or enum.
+#[derive(Default)]
+pub struct Container {
+    pub tag: Option<String>,
+    pub content: Option<String>,
+}
+
+/// `#[strata(...)]` on an enum variant.
+#[derive(Default)]
+pub struct VariantAttrs {
+    pub rename: Option<String>,
+}
+
+/// `#[strata(...)]` on a field.
+#[derive(Default)]
+pub struct FieldAttrs {
+    pub rename: Option<String>,
+    pub skip: bool,
+    pub flatten: bool,
+    pub skip_none: bool,
+}
+
+impl Container {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container::default();
+
+        for attr in strata_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    container.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    container.content = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    return Err(meta.error("unknown strata container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if container.content.is_some() && container.tag.is_none() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`content` needs a `tag` to go with it",
+            ));
+        }
+
+        Ok(container)
+    }
+}
+
+impl VariantAttrs {
+    pub fn parse(variant: &Variant) -> Result<Self> {
+        let mut attrs = VariantAttrs::default();
+
+        for attr in strata_attrs(&variant.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    return Err(meta.error("unknown strata variant attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(attrs)
+    }
+}
+
+impl FieldAttrs {
+    pub fn parse(field: &Field) -> Result<Self> {
+        let mut attrs = FieldAttrs::default();
+
+        for attr in strata_attrs(&field.attrs) {
+            if field.ident.is_none() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "strata attributes are only supported on named fields",
+                ));
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                } else if meta.path.is_ident("skip_none") {
+                    attrs.skip_none = true;
+                } else {
+                    return Err(meta.error("unknown strata field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let conflict = if attrs.skip && (attrs.rename.is_some() || attrs.flatten || attrs.skip_none)
+        {
+            Some("`skip` cannot be combined with other attributes")
+        } else if attrs.flatten && attrs.rename.is_some() {
+            Some("a flattened field has no key to rename")
+        } else if attrs.flatten && attrs.skip_none {
+            Some("`skip_none` does not apply to flattened fields")
+        } else if attrs.skip_none && crate::option_inner(&field.ty).is_none() {
+            Some("`skip_none` only applies to `Option` fields")
+        } else {
+            None
+        };
+
+        match conflict {
+            Some(message) => Err(syn::Error::new_spanned(field, message)),
+            None => Ok(attrs),
+        }
+    }
+}
+
+fn strata_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("strata"))
+}
@@ -0,0 +1,688 @@
+use std::collections::BTreeSet;
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    Data, DataEnum, DeriveInput, Error, Fields, Generics, Member, Result, Type, WherePredicate,
+    parse_quote, parse_quote_spanned,
+};
+
+use crate::attr::{Container, FieldAttrs, VariantAttrs};
+use crate::option_inner;
+
+/// How an enum's variant name is written.
+enum Tagging {
+    /// `"Unit"` or `{Variant: content}`.
+    External,
+    /// `{tag: "Variant", ...fields}`.
+    Internal(String),
+    /// `{tag: "Variant", content: content}`.
+    Adjacent(String, String),
+}
+
+struct FieldInfo<'a> {
+    member: Member,
+    /// What the field is bound to in generated code.
+    binding: Ident,
+    key: String,
+    ty: &'a Type,
+    attrs: FieldAttrs,
+}
+
+struct VariantInfo<'a> {
+    ident: &'a Ident,
+    key: String,
+    shape: &'a Fields,
+    fields: Vec<FieldInfo<'a>>,
+}
+
+pub fn to_strata(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+
+    // structs with named fields and tagged enums are always maps, and
+    // implement `IntoMap` so they can be flattened
+    let (body, is_map, map_types) = match &input.data {
+        Data::Struct(data) => {
+            struct_tagging(input)?;
+            let fields = field_infos(&data.fields)?;
+            let pattern = pattern(quote!(#name), &data.fields, &fields);
+            let is_map = matches!(data.fields, Fields::Named(_));
+            let value = if is_map {
+                fields_to_map(&fields)
+            } else {
+                fields_to_value(&data.fields, &fields)
+            };
+            let body = quote! {
+                let #pattern = __value;
+                #value
+            };
+            (body, is_map, flattened_types(&fields))
+        }
+        Data::Enum(data) => {
+            let tagging = enum_tagging(input)?;
+            let variants = variant_infos(data, &tagging)?;
+            let arms = variants
+                .iter()
+                .map(|variant| variant_arm(name, variant, &tagging));
+            let body = quote! {
+                match __value {
+                    #(#arms)*
+                }
+            };
+
+            let mut map_types = Vec::new();
+            for variant in &variants {
+                map_types.extend(flattened_types(&variant.fields));
+                if matches!(tagging, Tagging::Internal(_))
+                    && matches!(variant.shape, Fields::Unnamed(_))
+                {
+                    map_types.push(variant.fields[0].ty);
+                }
+            }
+            (body, !matches!(tagging, Tagging::External), map_types)
+        }
+        Data::Union(_) => return Err(Error::new_spanned(input, "unions are not supported")),
+    };
+
+    let mut generics = with_bounds(&input.generics, |param| {
+        vec![parse_quote!(#param: ::core::convert::Into<::strata::value::Value>)]
+    });
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(map_types.into_iter().map(|ty| -> WherePredicate {
+            parse_quote_spanned!(ty.span()=> #ty: ::strata::convert::IntoMap)
+        }));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    if !is_map {
+        return Ok(quote! {
+            impl #impl_generics ::core::convert::From<#name #ty_generics> for ::strata::value::Value
+                #where_clause
+            {
+                fn from(__value: #name #ty_generics) -> Self {
+                    #body
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::strata::convert::IntoMap for #name #ty_generics
+            #where_clause
+        {
+            fn into_map(
+                self,
+            ) -> ::std::collections::BTreeMap<::std::string::String, ::strata::value::Value> {
+                let __value = self;
+                #body
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for ::strata::value::Value
+            #where_clause
+        {
+            fn from(__value: #name #ty_generics) -> Self {
+                ::strata::value::Value::Map(::strata::convert::IntoMap::into_map(__value))
+            }
+        }
+    })
+}
+
+pub fn from_strata(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            struct_tagging(input)?;
+            let fields = field_infos(&data.fields)?;
+            fields_from_value(
+                quote!(Self),
+                &data.fields,
+                &fields,
+                quote!(__value),
+                TokenStream::new(),
+            )
+        }
+        Data::Enum(data) => {
+            let tagging = enum_tagging(input)?;
+            let variants = variant_infos(data, &tagging)?;
+            enum_from_value(&variants, &tagging)
+        }
+        Data::Union(_) => return Err(Error::new_spanned(input, "unions are not supported")),
+    };
+
+    let generics = with_bounds(&input.generics, |param| {
+        vec![
+            parse_quote!(#param: ::core::convert::TryFrom<::strata::value::Value>),
+            parse_quote! {
+                ::strata::error::ConvertError: ::core::convert::From<
+                    <#param as ::core::convert::TryFrom<::strata::value::Value>>::Error,
+                >
+            },
+        ]
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::TryFrom<::strata::value::Value> for #name #ty_generics
+            #where_clause
+        {
+            type Error = ::strata::error::ConvertError;
+
+            #[allow(unused_mut)]
+            fn try_from(
+                __value: ::strata::value::Value,
+            ) -> ::core::result::Result<Self, ::strata::error::ConvertError> {
+                #body
+            }
+        }
+    })
+}
+
+fn struct_tagging(input: &DeriveInput) -> Result<()> {
+    let container = Container::parse(&input.attrs)?;
+    if container.tag.is_some() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`tag` and `content` only apply to enums",
+        ));
+    }
+    Ok(())
+}
+
+fn enum_tagging(input: &DeriveInput) -> Result<Tagging> {
+    let container = Container::parse(&input.attrs)?;
+    Ok(match (container.tag, container.content) {
+        (None, _) => Tagging::External,
+        (Some(tag), None) => Tagging::Internal(tag),
+        (Some(tag), Some(content)) => {
+            if tag == content {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "`tag` and `content` must be different keys",
+                ));
+            }
+            Tagging::Adjacent(tag, content)
+        }
+    })
+}
+
+fn with_bounds(generics: &Generics, bounds: impl Fn(&Ident) -> Vec<WherePredicate>) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+
+    let where_clause = generics.make_where_clause();
+    for param in &params {
+        where_clause.predicates.extend(bounds(param));
+    }
+    generics
+}
+
+fn field_infos(fields: &Fields) -> Result<Vec<FieldInfo<'_>>> {
+    let mut infos = Vec::new();
+    let mut keys = BTreeSet::new();
+    let mut flattened = false;
+
+    for (index, field) in fields.iter().enumerate() {
+        let attrs = FieldAttrs::parse(field)?;
+        let (member, key) = match &field.ident {
+            Some(ident) => {
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name).to_string();
+                (
+                    Member::Named(ident.clone()),
+                    attrs.rename.clone().unwrap_or(name),
+                )
+            }
+            None => (Member::Unnamed(index.into()), index.to_string()),
+        };
+
+        if field.ident.is_some() && !attrs.skip && !attrs.flatten && !keys.insert(key.clone()) {
+            return Err(Error::new_spanned(
+                field,
+                format!("duplicate key `{}`", key),
+            ));
+        }
+
+        // two flattened fields would both read the keys the other wrote
+        if attrs.flatten && std::mem::replace(&mut flattened, true) {
+            return Err(Error::new_spanned(field, "only one field can be flattened"));
+        }
+
+        infos.push(FieldInfo {
+            member,
+            binding: format_ident!("__field{}", index),
+            key,
+            ty: &field.ty,
+            attrs,
+        });
+    }
+
+    Ok(infos)
+}
+
+fn variant_infos<'a>(data: &'a DataEnum, tagging: &Tagging) -> Result<Vec<VariantInfo<'a>>> {
+    let mut infos = Vec::new();
+    let mut keys = BTreeSet::new();
+
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(variant)?;
+        let key = attrs.rename.unwrap_or_else(|| variant.ident.to_string());
+        if !keys.insert(key.clone()) {
+            return Err(Error::new_spanned(
+                variant,
+                format!("duplicate variant name `{}`", key),
+            ));
+        }
+
+        let fields = field_infos(&variant.fields)?;
+
+        if let Tagging::Internal(tag) = tagging {
+            if matches!(variant.fields, Fields::Unnamed(_)) && fields.len() != 1 {
+                return Err(Error::new_spanned(
+                    variant,
+                    "internally tagged enums cannot have tuple variants",
+                ));
+            }
+            if let Some(field) = fields.iter().find(|field| {
+                matches!(field.member, Member::Named(_))
+                    && !field.attrs.skip
+                    && !field.attrs.flatten
+                    && &field.key == tag
+            }) {
+                return Err(Error::new_spanned(
+                    &field.member,
+                    format!("field key `{}` is the enum's tag", field.key),
+                ));
+            }
+        }
+
+        infos.push(VariantInfo {
+            ident: &variant.ident,
+            key,
+            shape: &variant.fields,
+            fields,
+        });
+    }
+
+    Ok(infos)
+}
+
+/// A pattern binding every field that is not skipped.
+fn pattern(path: TokenStream, shape: &Fields, fields: &[FieldInfo]) -> TokenStream {
+    let bindings = fields.iter().map(|field| {
+        let binding = if field.attrs.skip {
+            quote!(_)
+        } else {
+            let binding = &field.binding;
+            quote!(#binding)
+        };
+        match &field.member {
+            Member::Named(ident) => quote!(#ident: #binding),
+            Member::Unnamed(_) => binding,
+        }
+    });
+
+    match shape {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+        Fields::Unit => path,
+    }
+}
+
+fn key_string(key: &str) -> TokenStream {
+    quote!(::std::string::String::from(#key))
+}
+
+fn new_map() -> TokenStream {
+    quote! {
+        let mut __map: ::std::collections::BTreeMap<
+            ::std::string::String,
+            ::strata::value::Value,
+        > = ::std::collections::BTreeMap::new();
+    }
+}
+
+/// Types that must implement `IntoMap` because they are flattened.
+fn flattened_types<'a>(fields: &[FieldInfo<'a>]) -> Vec<&'a Type> {
+    fields
+        .iter()
+        .filter(|field| field.attrs.flatten)
+        .map(|field| field.ty)
+        .collect()
+}
+
+/// Statements inserting named fields into `__map`. Flattened fields go
+/// last, so the other fields keep any key they share with it.
+fn insert_fields(fields: &[FieldInfo]) -> TokenStream {
+    let inserts = fields
+        .iter()
+        .filter(|field| !field.attrs.skip && !field.attrs.flatten)
+        .map(|field| {
+            let binding = &field.binding;
+            let key = key_string(&field.key);
+
+            if field.attrs.skip_none {
+                quote! {
+                    if let ::core::option::Option::Some(__some) = #binding {
+                        __map.insert(#key, ::core::convert::Into::into(__some));
+                    }
+                }
+            } else {
+                quote! {
+                    __map.insert(#key, ::core::convert::Into::into(#binding));
+                }
+            }
+        });
+
+    let flattened = fields
+        .iter()
+        .filter(|field| field.attrs.flatten)
+        .map(|field| flatten(&field.binding));
+
+    quote!(#(#inserts)* #(#flattened)*)
+}
+
+fn flatten(binding: &Ident) -> TokenStream {
+    quote! {
+        ::strata::convert::__private::flatten_into(
+            &mut __map,
+            ::strata::convert::IntoMap::into_map(#binding),
+        );
+    }
+}
+
+/// A block building `__map` from bound named fields.
+fn fields_to_map(fields: &[FieldInfo]) -> TokenStream {
+    let new_map = new_map();
+    let inserts = insert_fields(fields);
+    quote! {{
+        #new_map
+        #inserts
+        __map
+    }}
+}
+
+/// An expression converting bound fields into one value: named fields are
+/// a map, a single unnamed field is transparent, several are a list.
+fn fields_to_value(shape: &Fields, fields: &[FieldInfo]) -> TokenStream {
+    match shape {
+        Fields::Named(_) => {
+            let map = fields_to_map(fields);
+            quote!(::strata::value::Value::Map(#map))
+        }
+        Fields::Unnamed(_) if fields.len() == 1 => {
+            let binding = &fields[0].binding;
+            quote!(::core::convert::Into::into(#binding))
+        }
+        Fields::Unnamed(_) => {
+            let bindings = fields.iter().map(|field| &field.binding);
+            quote! {
+                ::strata::value::Value::List(::std::vec![
+                    #(::core::convert::Into::into(#bindings)),*
+                ])
+            }
+        }
+        Fields::Unit => quote!(::strata::value::Value::Null),
+    }
+}
+
+/// A match arm converting one variant: to a value when externally tagged,
+/// otherwise to the map that holds the tag.
+fn variant_arm(name: &Ident, variant: &VariantInfo, tagging: &Tagging) -> TokenStream {
+    let ident = variant.ident;
+    let pattern = pattern(quote!(#name::#ident), variant.shape, &variant.fields);
+    let variant_key = &variant.key;
+    let name_value = quote!(::strata::value::Value::String(#variant_key.to_string()));
+    let new_map = new_map();
+
+    let value = match tagging {
+        Tagging::External => match variant.shape {
+            Fields::Unit => name_value,
+            shape => {
+                let content = fields_to_value(shape, &variant.fields);
+                let key = key_string(variant_key);
+                quote! {
+                    ::strata::value::Value::Map(::std::collections::BTreeMap::from([
+                        (#key, #content),
+                    ]))
+                }
+            }
+        },
+        // the tag goes in first, so a flattened key cannot replace it
+        Tagging::Internal(tag) => {
+            let tag = key_string(tag);
+            let fields = match variant.shape {
+                Fields::Named(_) => insert_fields(&variant.fields),
+                Fields::Unnamed(_) => flatten(&variant.fields[0].binding),
+                Fields::Unit => TokenStream::new(),
+            };
+            quote! {{
+                #new_map
+                __map.insert(#tag, #name_value);
+                #fields
+                __map
+            }}
+        }
+        Tagging::Adjacent(tag, content) => {
+            let tag = key_string(tag);
+            let content = match variant.shape {
+                Fields::Unit => TokenStream::new(),
+                shape => {
+                    let key = key_string(content);
+                    let value = fields_to_value(shape, &variant.fields);
+                    quote!(__map.insert(#key, #value);)
+                }
+            };
+            quote! {{
+                #new_map
+                __map.insert(#tag, #name_value);
+                #content
+                __map
+            }}
+        }
+    };
+
+    quote!(#pattern => #value,)
+}
+
+fn in_field(key: &str) -> TokenStream {
+    quote!(.map_err(|__err| __err.in_field(#key)))
+}
+
+/// A block building `constructor` from `source`, the inverse of
+/// `fields_to_value`. Each fallible step is followed by `wrap`, then `?`.
+fn fields_from_value(
+    constructor: TokenStream,
+    shape: &Fields,
+    fields: &[FieldInfo],
+    source: TokenStream,
+    wrap: TokenStream,
+) -> TokenStream {
+    match shape {
+        Fields::Named(_) => {
+            let build = fields_from_map(constructor, fields, &wrap);
+            quote! {{
+                let mut __map = ::strata::convert::__private::expect_map(#source) #wrap ?;
+                #build
+            }}
+        }
+        Fields::Unnamed(_) if fields.len() == 1 => {
+            let ty = fields[0].ty;
+            quote! {
+                ::core::result::Result::Ok(#constructor(
+                    ::strata::convert::__private::convert::<#ty>(#source) #wrap ?
+                ))
+            }
+        }
+        Fields::Unnamed(_) => {
+            let len = fields.len();
+            let elements = fields.iter().enumerate().map(|(index, field)| {
+                let ty = field.ty;
+                quote! {
+                    ::strata::convert::__private::element::<#ty>(__items.next().unwrap(), #index) #wrap ?
+                }
+            });
+            quote! {{
+                let mut __items = ::strata::convert::__private::expect_len(#source, #len) #wrap ?
+                    .into_iter();
+                ::core::result::Result::Ok(#constructor(#(#elements),*))
+            }}
+        }
+        Fields::Unit => quote! {{
+            ::strata::convert::__private::expect_null(#source) #wrap ?;
+            ::core::result::Result::Ok(#constructor)
+        }},
+    }
+}
+
+/// Statements taking named fields out of `__map`, ending in the
+/// constructed value. The flattened field sees whatever the others left.
+fn fields_from_map(
+    constructor: TokenStream,
+    fields: &[FieldInfo],
+    wrap: &TokenStream,
+) -> TokenStream {
+    let take = fields
+        .iter()
+        .filter(|field| !field.attrs.skip && !field.attrs.flatten)
+        .map(|field| {
+            let binding = &field.binding;
+            let key = &field.key;
+            match option_inner(field.ty) {
+                Some(inner) => quote! {
+                    let #binding = ::strata::convert::__private::take_optional::<#inner>(&mut __map, #key)
+                        #wrap ?;
+                },
+                None => {
+                    let ty = field.ty;
+                    quote! {
+                        let #binding = ::strata::convert::__private::take_field::<#ty>(&mut __map, #key)
+                            #wrap ?;
+                    }
+                }
+            }
+        });
+
+    // the one flattened field takes whatever the others left
+    let rest = fields
+        .iter()
+        .filter(|field| field.attrs.skip || field.attrs.flatten)
+        .map(|field| {
+            let binding = &field.binding;
+            let ty = field.ty;
+            if field.attrs.skip {
+                quote!(let #binding = ::core::default::Default::default();)
+            } else {
+                quote! {
+                    let #binding = ::strata::convert::__private::convert::<#ty>(
+                        ::strata::value::Value::Map(__map),
+                    ) #wrap ?;
+                }
+            }
+        });
+
+    let inits = fields.iter().map(|field| {
+        let member = &field.member;
+        let binding = &field.binding;
+        quote!(#member: #binding)
+    });
+
+    quote! {
+        #(#take)*
+        #(#rest)*
+        ::core::result::Result::Ok(#constructor { #(#inits),* })
+    }
+}
+
+fn enum_from_value(variants: &[VariantInfo], tagging: &Tagging) -> TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let ident = variant.ident;
+        let key = &variant.key;
+        let constructor = quote!(Self::#ident);
+
+        let build = match (tagging, variant.shape) {
+            (Tagging::External, Fields::Unit) => {
+                let wrap = in_field(key);
+                quote! {{
+                    if let ::core::option::Option::Some(__content) = __content {
+                        ::strata::convert::__private::expect_null(__content) #wrap ?;
+                    }
+                    ::core::result::Result::Ok(#constructor)
+                }}
+            }
+            (Tagging::External, shape) => {
+                let build = fields_from_value(
+                    constructor,
+                    shape,
+                    &variant.fields,
+                    quote!(__content),
+                    in_field(key),
+                );
+                quote! {{
+                    let __content = ::strata::convert::__private::variant_content(__content)?;
+                    #build
+                }}
+            }
+            (_, Fields::Unit) => quote!(::core::result::Result::Ok(#constructor)),
+            (Tagging::Internal(_), Fields::Named(_)) => {
+                let build = fields_from_map(constructor, &variant.fields, &TokenStream::new());
+                quote!({ #build })
+            }
+            (Tagging::Internal(_), _) => {
+                let ty = variant.fields[0].ty;
+                quote! {
+                    ::core::result::Result::Ok(#constructor(
+                        ::strata::convert::__private::convert::<#ty>(::strata::value::Value::Map(__map))?
+                    ))
+                }
+            }
+            (Tagging::Adjacent(_, content), shape) => {
+                let build = fields_from_value(
+                    constructor,
+                    shape,
+                    &variant.fields,
+                    quote!(__content),
+                    in_field(content),
+                );
+                quote! {{
+                    let __content: ::strata::value::Value =
+                        ::strata::convert::__private::take_field(&mut __map, #content)?;
+                    #build
+                }}
+            }
+        };
+
+        quote!(#key => #build,)
+    });
+
+    let (start, unknown) = match tagging {
+        Tagging::External => (
+            quote! {
+                let (__name, __content) = ::strata::convert::__private::external_variant(__value)?;
+            },
+            quote!(::strata::convert::__private::unknown_variant(__name)),
+        ),
+        Tagging::Internal(tag) | Tagging::Adjacent(tag, _) => (
+            quote! {
+                let mut __map = ::strata::convert::__private::expect_map(__value)?;
+                let __name: ::std::string::String =
+                    ::strata::convert::__private::take_field(&mut __map, #tag)?;
+            },
+            quote!(::strata::convert::__private::unknown_variant(__name).in_field(#tag)),
+        ),
+    };
+
+    quote! {
+        #start
+        match __name.as_str() {
+            #(#arms)*
+            _ => ::core::result::Result::Err(#unknown),
+        }
+    }
+}
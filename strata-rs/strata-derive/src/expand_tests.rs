@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod tests {
+    use syn::{DeriveInput, parse_quote};
+
+    use crate::expand::{from_strata, to_strata};
+
+    fn error(input: DeriveInput) -> String {
+        let to = to_strata(&input).unwrap_err().to_string();
+        let from = from_strata(&input).unwrap_err().to_string();
+        assert_eq!(to, from);
+        to
+    }
+
+    #[test]
+    fn valid_input_expands() {
+        let input: DeriveInput = parse_quote! {
+            #[strata(tag = "t", content = "c")]
+            enum Message<T> {
+                Ping,
+                Text(T),
+                Span {
+                    #[strata(rename = "start")]
+                    from: u8,
+                    #[strata(skip_none)]
+                    to: Option<u8>,
+                },
+            }
+        };
+
+        assert!(to_strata(&input).is_ok());
+        assert!(from_strata(&input).is_ok());
+    }
+
+    #[test]
+    fn unknown_attributes() {
+        assert_eq!(
+            error(parse_quote! {
+                struct Service {
+                    #[strata(default)]
+                    name: String,
+                }
+            }),
+            "unknown strata field attribute"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[strata(rename_all = "lowercase")]
+                struct Service;
+            }),
+            "unknown strata container attribute"
+        );
+    }
+
+    #[test]
+    fn conflicting_field_attributes() {
+        assert_eq!(
+            error(parse_quote! {
+                struct Service {
+                    #[strata(skip, rename = "n")]
+                    name: String,
+                }
+            }),
+            "`skip` cannot be combined with other attributes"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                struct Service {
+                    #[strata(skip_none)]
+                    name: String,
+                }
+            }),
+            "`skip_none` only applies to `Option` fields"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                struct Pair(#[strata(skip)] u8, u8);
+            }),
+            "strata attributes are only supported on named fields"
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert_eq!(
+            error(parse_quote! {
+                struct Service {
+                    name: String,
+                    #[strata(rename = "name")]
+                    label: String,
+                }
+            }),
+            "duplicate key `name`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Mode {
+                    Active,
+                    #[strata(rename = "Active")]
+                    On,
+                }
+            }),
+            "duplicate variant name `Active`"
+        );
+    }
+
+    #[test]
+    fn tagging_rules() {
+        assert_eq!(
+            error(parse_quote! {
+                #[strata(tag = "type")]
+                struct Service;
+            }),
+            "`tag` and `content` only apply to enums"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[strata(content = "c")]
+                enum Mode {}
+            }),
+            "`content` needs a `tag` to go with it"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[strata(tag = "type")]
+                enum Event {
+                    Moved(i64, i64),
+                }
+            }),
+            "internally tagged enums cannot have tuple variants"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[strata(tag = "type")]
+                enum Event {
+                    Typed { r#type: String },
+                }
+            }),
+            "field key `type` is the enum's tag"
+        );
+    }
+
+    #[test]
+    fn maps_are_required_where_flattened() {
+        let input: DeriveInput = parse_quote! {
+            #[strata(tag = "type")]
+            enum Event {
+                Moved {
+                    #[strata(flatten)]
+                    extra: Vec<i64>,
+                },
+                Ping(i64),
+            }
+        };
+        let expanded = to_strata(&input).unwrap().to_string();
+
+        assert!(expanded.contains("Vec < i64 > : :: strata :: convert :: IntoMap"));
+        assert!(expanded.contains("i64 : :: strata :: convert :: IntoMap"));
+    }
+
+    #[test]
+    fn one_flattened_field() {
+        assert_eq!(
+            error(parse_quote! {
+                struct Service {
+                    #[strata(flatten)]
+                    limits: Limits,
+                    #[strata(flatten)]
+                    extra: BTreeMap<String, Value>,
+                }
+            }),
+            "only one field can be flattened"
+        );
+    }
+}
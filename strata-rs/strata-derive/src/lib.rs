@@ -0,0 +1,58 @@
+//! `#[derive(ToStrata, FromStrata)]` for `strata-rs`.
+//!
+//! The derives implement `From<T> for Value` and `TryFrom<Value> for T`,
+//! plus `IntoMap` for types that always convert to a map.
+//! Generated code names the runtime crate as `::strata`, the library name
+//! of `strata-rs`, and is reached through the crate's `derive` feature
+//! rather than by depending on this crate directly.
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, GenericArgument, PathArguments, Type, parse_macro_input};
+
+mod attr;
+mod expand;
+
+#[cfg(test)]
+mod expand_tests;
+
+#[proc_macro_derive(ToStrata, attributes(strata))]
+pub fn derive_to_strata(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand::to_strata(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromStrata, attributes(strata))]
+pub fn derive_from_strata(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand::from_strata(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The `T` of a field declared as `Option<T>`.
+///
+/// Matched by name, as a derive cannot resolve types: a type alias for
+/// `Option` is treated as a required field.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+
+    let last = path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+
+    match &last.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
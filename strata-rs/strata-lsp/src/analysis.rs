@@ -0,0 +1,303 @@
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, FoldingRange, FoldingRangeKind, Hover, HoverContents,
+    MarkupContent, MarkupKind, NumberOrString, Position, Range, SemanticToken, SemanticTokenType,
+    SemanticTokensLegend, TextEdit,
+};
+use strata::cst::{self, Node};
+use strata::format::format_document;
+use strata::hash::hash_value;
+use strata::lexer::{Lexer, TokenKind};
+use strata::parser::parse_recovering;
+use strata::value::Value;
+
+use crate::line_index::{LineIndex, utf16_len};
+
+/// Token types in the order their indexes are sent to the client.
+pub const TOKEN_TYPES: [SemanticTokenType; 5] = [
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::COMMENT,
+];
+
+const PROPERTY: u32 = 0;
+const STRING: u32 = 1;
+const NUMBER: u32 = 2;
+const KEYWORD: u32 = 3;
+const COMMENT: u32 = 4;
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    }
+}
+
+/// Every parse error in the document, as reported by `strata compile`.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let index = LineIndex::new(text);
+
+    parse_recovering(text)
+        .errors
+        .into_iter()
+        .map(|err| {
+            let start = err.span.offset.min(text.len());
+            // underline the offending character, unless the error is at a
+            // line break or the end of input
+            let end = match text[start..].chars().next() {
+                Some(ch) if ch != '\n' && ch != '\r' => start + ch.len_utf8(),
+                _ => start,
+            };
+
+            Diagnostic {
+                range: Range::new(index.position(start), index.position(end)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(err.code().to_string())),
+                source: Some("strata".to_string()),
+                message: err.kind.to_string(),
+                ..Diagnostic::default()
+            }
+        })
+        .collect()
+}
+
+/// Replaces the document with `strata fmt` output, comments included.
+///
+/// Documents that do not parse are left alone and `None` is returned.
+pub fn format(text: &str) -> Option<Vec<TextEdit>> {
+    let document = cst::parse(text).ok()?;
+
+    let formatted = format_document(&document);
+    if formatted == text {
+        return Some(Vec::new());
+    }
+
+    let index = LineIndex::new(text);
+    Some(vec![TextEdit {
+        range: Range::new(Position::new(0, 0), index.end()),
+        new_text: formatted,
+    }])
+}
+
+/// One range per bracketed map or list that spans several lines. The
+/// closing bracket stays visible.
+pub fn folding_ranges(text: &str) -> Vec<FoldingRange> {
+    let Ok(document) = cst::parse(text) else {
+        return Vec::new();
+    };
+
+    let mut ranges = Vec::new();
+    collect_folds(&document.root, &mut ranges);
+    ranges
+}
+
+fn collect_folds(node: &Node, ranges: &mut Vec<FoldingRange>) {
+    let (open, close) = match node {
+        Node::Scalar(_) => return,
+        Node::List(list) => {
+            for item in &list.items {
+                collect_folds(&item.value, ranges);
+            }
+            (Some(&list.open), Some(&list.close))
+        }
+        Node::Map(map) => {
+            for entry in &map.entries {
+                collect_folds(&entry.value, ranges);
+            }
+            (map.open.as_ref(), map.close.as_ref())
+        }
+    };
+
+    // the shorthand root map has no brackets to fold
+    let (Some(open), Some(close)) = (open, close) else {
+        return;
+    };
+
+    // spans are 1-based, folding lines 0-based; fold up to the line
+    // before the closing bracket
+    if close.span.line > open.span.line + 1 {
+        ranges.push(FoldingRange {
+            start_line: open.span.line as u32 - 1,
+            end_line: close.span.line as u32 - 2,
+            kind: Some(FoldingRangeKind::Region),
+            ..FoldingRange::default()
+        });
+    }
+}
+
+/// The innermost value under the cursor, with its canonical hash.
+///
+/// Hovering a key describes the entry's value; hovering anything outside
+/// a nested value describes the whole document.
+pub fn hover(text: &str, position: Position) -> Option<Hover> {
+    let document = cst::parse(text).ok()?;
+    let offset = LineIndex::new(text).offset(position);
+
+    let node = enclosing(&document.root, offset).unwrap_or(&document.root);
+    let value = node.to_value();
+
+    let hash: String = hash_value(&value)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let index = LineIndex::new(text);
+    let range = node.range();
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("**{}**\n\nblake3 `{}`", describe(&value), hash),
+        }),
+        range: Some(Range::new(
+            index.position(range.start.offset),
+            index.position(range.end.offset),
+        )),
+    })
+}
+
+fn enclosing(node: &Node, offset: usize) -> Option<&Node> {
+    let range = node.range();
+    // the end is inclusive so a cursor just after a value still counts
+    if offset < range.start.offset || offset > range.end.offset {
+        return None;
+    }
+
+    let inner = match node {
+        Node::Scalar(_) => None,
+        Node::List(list) => list
+            .items
+            .iter()
+            .find_map(|item| enclosing(&item.value, offset)),
+        Node::Map(map) => map.entries.iter().find_map(|entry| {
+            let key = entry.key.range();
+            if (key.start.offset..=key.end.offset).contains(&offset) {
+                Some(&entry.value)
+            } else {
+                enclosing(&entry.value, offset)
+            }
+        }),
+    };
+
+    inner.or(Some(node))
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "bool".to_string(),
+        Value::Int(_) => "int".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Bytes(bytes) => format!("bytes ({} bytes)", bytes.len()),
+        Value::List(items) => format!("list ({} items)", items.len()),
+        Value::Map(map) => format!("map ({} entries)", map.len()),
+    }
+}
+
+/// Semantic tokens for the whole document.
+///
+/// Works from the lexer alone, so highlighting survives parse errors.
+/// Keys, whether bare or quoted, are told apart from string values by
+/// the ':' or '{' that follows them.
+pub fn semantic_tokens(text: &str) -> Vec<SemanticToken> {
+    // (start, end, token type) in byte offsets
+    let mut spans: Vec<(usize, usize, u32)> = Vec::new();
+    let mut lexer = Lexer::new(text);
+    let mut end = 0;
+
+    loop {
+        let before = lexer.offset();
+
+        match lexer.next_token() {
+            Ok(token) => {
+                for (start, stop) in comments_in(text, end, token.span.offset) {
+                    spans.push((start, stop, COMMENT));
+                }
+
+                let token_type = match &token.kind {
+                    TokenKind::EOF => break,
+                    TokenKind::Ident(_) => Some(PROPERTY),
+                    TokenKind::String(_) => Some(STRING),
+                    TokenKind::Int(_) => Some(NUMBER),
+                    TokenKind::Bytes(_) if text[token.span.offset..].starts_with("0x") => {
+                        Some(NUMBER)
+                    }
+                    TokenKind::Bytes(_) => Some(STRING),
+                    TokenKind::Null | TokenKind::True | TokenKind::False => Some(KEYWORD),
+                    TokenKind::Colon | TokenKind::LBrace => {
+                        // the token before this one was a key
+                        if let Some(last) = spans.last_mut()
+                            && last.2 == STRING
+                        {
+                            last.2 = PROPERTY;
+                        }
+                        None
+                    }
+                    _ => None,
+                };
+
+                if let Some(token_type) = token_type {
+                    spans.push((token.span.offset, lexer.offset(), token_type));
+                }
+            }
+
+            // the lexer has skipped the bad input, carry on after it
+            Err(_) => {
+                if lexer.offset() == before {
+                    break;
+                }
+            }
+        }
+
+        end = lexer.offset();
+    }
+
+    encode_tokens(text, &spans)
+}
+
+// relative positions, as the protocol sends them
+fn encode_tokens(text: &str, spans: &[(usize, usize, u32)]) -> Vec<SemanticToken> {
+    let index = LineIndex::new(text);
+    let mut tokens = Vec::with_capacity(spans.len());
+    let mut previous = Position::new(0, 0);
+
+    for &(start, end, token_type) in spans {
+        let position = index.position(start);
+        let delta_line = position.line - previous.line;
+        let delta_start = if delta_line == 0 {
+            position.character - previous.character
+        } else {
+            position.character
+        };
+
+        tokens.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: utf16_len(&text[start..end]),
+            token_type,
+            token_modifiers_bitset: 0,
+        });
+        previous = position;
+    }
+
+    tokens
+}
+
+// `#` and `//` comments between two tokens, without their line breaks
+fn comments_in(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut offset = start;
+
+    // between tokens there is only whitespace and comments, so the first
+    // comment marker on a line starts a comment that runs to its end
+    for line in text[start..end].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if let Some(at) = content.find(['#', '/']) {
+            found.push((offset + at, offset + content.len()));
+        }
+        offset += line.len();
+    }
+
+    found
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use lsp_types::{
+        FoldingRange, FoldingRangeKind, HoverContents, NumberOrString, Position, Range,
+        SemanticToken,
+    };
+
+    use crate::analysis::{diagnostics, folding_ranges, format, hover, semantic_tokens};
+    use crate::line_index::LineIndex;
+
+    const CONFIG: &str = "service {\n  retries: 3\n  ports: [\n    80,\n    443,\n  ]\n}\n";
+
+    fn hover_text(text: &str, position: Position) -> String {
+        match hover(text, position).unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            other => panic!("unexpected hover contents {:?}", other),
+        }
+    }
+
+    #[test]
+    fn line_index_counts_utf16_units() {
+        let text = "a: \"é😀\"\nb: 1";
+        let index = LineIndex::new(text);
+
+        // é is one unit, 😀 two
+        assert_eq!(index.position(text.find('"').unwrap()), Position::new(0, 3));
+        assert_eq!(
+            index.position(text.rfind('"').unwrap()),
+            Position::new(0, 7)
+        );
+        assert_eq!(index.position(text.len()), Position::new(1, 4));
+
+        assert_eq!(index.offset(Position::new(0, 7)), text.rfind('"').unwrap());
+        assert_eq!(index.offset(Position::new(1, 0)), text.find('b').unwrap());
+        // past the end of a line or of the document clamps
+        assert_eq!(index.offset(Position::new(0, 99)), text.find('\n').unwrap());
+        assert_eq!(index.offset(Position::new(9, 0)), text.len());
+    }
+
+    #[test]
+    fn diagnostics_report_every_error() {
+        let found = diagnostics("{\n  retries 3\n  name: \"x\\q\"\n}\n");
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            found[0].range,
+            Range::new(Position::new(1, 10), Position::new(1, 11))
+        );
+        assert_eq!(
+            found[0].message,
+            "expected ':' after key `retries`, found integer 3"
+        );
+        assert_eq!(
+            found[0].code,
+            Some(NumberOrString::String("parse.unexpected_token".to_string()))
+        );
+        assert_eq!(found[0].source.as_deref(), Some("strata"));
+        assert_eq!(
+            found[1].code,
+            Some(NumberOrString::String("parse.invalid_escape".to_string()))
+        );
+
+        assert!(diagnostics(CONFIG).is_empty());
+    }
+
+    #[test]
+    fn diagnostics_at_end_of_input_are_empty_ranges() {
+        let found = diagnostics("[1, 2");
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].range,
+            Range::new(Position::new(0, 5), Position::new(0, 5))
+        );
+    }
+
+    #[test]
+    fn format_replaces_whole_document() {
+        let edits = format("{ b: 2, a: [1,2] }").unwrap();
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(0, 0), Position::new(0, 18))
+        );
+        assert_eq!(edits[0].new_text, "{\n  a: [1, 2]\n  b: 2\n}\n");
+
+        assert_eq!(format("{\n  a: [1, 2]\n  b: 2\n}\n").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn format_keeps_comments() {
+        let edits = format("# keep me\n{ b: 1, a: 2 } // trailing").unwrap();
+        assert_eq!(
+            edits[0].new_text,
+            "# keep me\n{\n  a: 2\n  b: 1\n} // trailing\n"
+        );
+    }
+
+    #[test]
+    fn format_leaves_errors_alone() {
+        assert_eq!(format("{ a 1 }"), None);
+    }
+
+    #[test]
+    fn folding_covers_multiline_brackets() {
+        let folds = folding_ranges(CONFIG);
+
+        let fold = |start_line, end_line| FoldingRange {
+            start_line,
+            end_line,
+            kind: Some(FoldingRangeKind::Region),
+            ..FoldingRange::default()
+        };
+        assert_eq!(folds, vec![fold(2, 4), fold(0, 5)]);
+
+        assert!(folding_ranges("{ a: [1, 2] }").is_empty());
+        assert!(folding_ranges("{\n  a 1\n}").is_empty());
+    }
+
+    #[test]
+    fn hover_shows_innermost_value_hash() {
+        // on `443`
+        let text = hover_text(CONFIG, Position::new(4, 5));
+        assert_eq!(
+            text,
+            format!(
+                "**int**\n\nblake3 `{}`",
+                blake3::hash(&strata::encode::encode(&strata::int!(443)).unwrap()).to_hex()
+            )
+        );
+
+        // a key describes its value
+        assert!(hover_text(CONFIG, Position::new(2, 3)).starts_with("**list (2 items)**"));
+        let range = hover(CONFIG, Position::new(2, 3)).unwrap().range.unwrap();
+        assert_eq!(range, Range::new(Position::new(2, 9), Position::new(5, 3)));
+
+        // outside any nested value is the whole document
+        assert!(hover_text(CONFIG, Position::new(7, 0)).starts_with("**map (1 entries)**"));
+
+        assert!(hover("{ a 1 }", Position::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn semantic_tokens_classify_keys_and_literals() {
+        let text = "# cfg\n\"content-type\": \"json\"\nflags { on: true, raw: 0x0a }\n";
+        let tokens = semantic_tokens(text);
+
+        let token = |delta_line, delta_start, length, token_type| SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type,
+            token_modifiers_bitset: 0,
+        };
+        assert_eq!(
+            tokens,
+            vec![
+                token(0, 0, 5, 4),  // # cfg
+                token(1, 0, 14, 0), // "content-type"
+                token(0, 16, 6, 1), // "json"
+                token(1, 0, 5, 0),  // flags
+                token(0, 8, 2, 0),  // on
+                token(0, 4, 4, 3),  // true
+                token(0, 6, 3, 0),  // raw
+                token(0, 5, 4, 2),  // 0x0a
+            ]
+        );
+    }
+
+    #[test]
+    fn semantic_tokens_survive_lex_errors() {
+        let tokens = semantic_tokens("{ a: \"x\\q\", b: 1 }");
+
+        let types: Vec<u32> = tokens.iter().map(|token| token.token_type).collect();
+        assert_eq!(types, vec![0, 0, 2]);
+    }
+}
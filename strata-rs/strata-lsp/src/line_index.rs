@@ -0,0 +1,63 @@
+use lsp_types::Position;
+
+/// Converts between byte offsets into a document and LSP positions.
+///
+/// LSP columns count UTF-16 code units, while the lexer works in bytes,
+/// so every position sent to or received from the client goes through
+/// here.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    // byte offset where each line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self { text, line_starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+
+        Position {
+            line: line as u32,
+            character: utf16_len(&self.text[start..offset]),
+        }
+    }
+
+    /// The byte offset of `position`, clamped to the end of its line and
+    /// to the end of the document.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let end = self
+            .line_starts
+            .get(position.line as usize + 1)
+            .map_or(self.text.len(), |next| next - 1);
+
+        let mut units = 0;
+        for (i, ch) in self.text[start..end].char_indices() {
+            if units >= position.character {
+                return start + i;
+            }
+            units += ch.len_utf16() as u32;
+        }
+
+        end
+    }
+
+    pub fn end(&self) -> Position {
+        self.position(self.text.len())
+    }
+}
+
+pub fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|ch| ch.len_utf16() as u32).sum()
+}
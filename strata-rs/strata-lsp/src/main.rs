@@ -0,0 +1,235 @@
+//! Language server for Strata Text (`.st`) files.
+//!
+//! Speaks LSP over stdio and provides diagnostics, formatting, folding,
+//! hover with canonical hashes, and semantic tokens. Every feature is a
+//! pure function of the document text in `analysis`; this file only
+//! tracks open documents and routes messages.
+
+mod analysis;
+mod line_index;
+
+#[cfg(test)]
+mod analysis_tests;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    FoldingRangeRequest, Formatting, HoverRequest, Request as RequestTrait,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentFormattingParams, FoldingRangeParams, FoldingRangeProviderCapability, HoverParams,
+    HoverProviderCapability, OneOf, PublishDiagnosticsParams, SemanticTokens,
+    SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        semantic_tokens_provider: Some(
+            SemanticTokensOptions {
+                legend: analysis::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            }
+            .into(),
+        ),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    Server {
+        connection: &connection,
+        documents: HashMap::new(),
+    }
+    .serve()?;
+
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    // full text of every open document; the client sends whole documents
+    documents: HashMap<Uri, String>,
+}
+
+impl Server<'_> {
+    fn serve(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.respond(request);
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.notify(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        let id = request.id.clone();
+
+        let result = match request.method.as_str() {
+            Formatting::METHOD => self.handle::<DocumentFormattingParams>(request, |text, _| {
+                serde_json::to_value(analysis::format(text))
+            }),
+            FoldingRangeRequest::METHOD => self.handle::<FoldingRangeParams>(request, |text, _| {
+                serde_json::to_value(analysis::folding_ranges(text))
+            }),
+            HoverRequest::METHOD => self.handle::<HoverParams>(request, |text, params| {
+                let position = params.text_document_position_params.position;
+                serde_json::to_value(analysis::hover(text, position))
+            }),
+            SemanticTokensFullRequest::METHOD => {
+                self.handle::<SemanticTokensParams>(request, |text, _| {
+                    serde_json::to_value(SemanticTokens {
+                        result_id: None,
+                        data: analysis::semantic_tokens(text),
+                    })
+                })
+            }
+            method => Err((
+                ErrorCode::MethodNotFound,
+                format!("unsupported request {}", method),
+            )),
+        };
+
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err((code, message)) => Response::new_err(id, code as i32, message),
+        }
+    }
+
+    fn handle<P: DocumentParams>(
+        &self,
+        request: Request,
+        answer: impl FnOnce(&str, &P) -> serde_json::Result<serde_json::Value>,
+    ) -> std::result::Result<serde_json::Value, (ErrorCode, String)> {
+        let params: P = serde_json::from_value(request.params)
+            .map_err(|err| (ErrorCode::InvalidParams, err.to_string()))?;
+
+        // requests for documents the client never opened get no answer
+        let Some(text) = self.documents.get(params.uri()) else {
+            return Ok(serde_json::Value::Null);
+        };
+
+        answer(text, &params).map_err(|err| (ErrorCode::InternalError, err.to_string()))
+    }
+
+    fn notify(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = params::<DidOpenTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                self.update(params.text_document.uri, params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = params::<DidChangeTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                // with full sync the last change holds the whole document
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(params.text_document.uri, change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = params::<DidCloseTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Uri, text: String) -> Result<()> {
+        let diagnostics = analysis::diagnostics(&text);
+        self.documents.insert(uri.clone(), text);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Uri, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+}
+
+/// Reads the parameters of a notification. A notification gets no reply, so
+/// malformed parameters are logged and the notification is dropped.
+fn params<P: serde::de::DeserializeOwned>(notification: Notification) -> Option<P> {
+    match serde_json::from_value(notification.params) {
+        Ok(params) => Some(params),
+        Err(err) => {
+            eprintln!("error: invalid {} params: {}", notification.method, err);
+            None
+        }
+    }
+}
+
+/// Request parameters that name a document.
+trait DocumentParams: serde::de::DeserializeOwned {
+    fn uri(&self) -> &Uri;
+}
+
+impl DocumentParams for DocumentFormattingParams {
+    fn uri(&self) -> &Uri {
+        &self.text_document.uri
+    }
+}
+
+impl DocumentParams for FoldingRangeParams {
+    fn uri(&self) -> &Uri {
+        &self.text_document.uri
+    }
+}
+
+impl DocumentParams for HoverParams {
+    fn uri(&self) -> &Uri {
+        &self.text_document_position_params.text_document.uri
+    }
+}
+
+impl DocumentParams for SemanticTokensParams {
+    fn uri(&self) -> &Uri {
+        &self.text_document.uri
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{Value, json};
+
+const URI: &str = "file:///config.st";
+
+/// A scripted client talking JSON-RPC to the server over its stdio.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_strata-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        };
+
+        let capabilities = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(capabilities["capabilities"]["hoverProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                assert!(message.get("error").is_none(), "{}", message);
+                return message["result"].clone();
+            }
+        }
+    }
+
+    fn diagnostics(&mut self) -> Vec<Value> {
+        loop {
+            let message = self.receive();
+            if message["method"] == "textDocument/publishDiagnostics" {
+                assert_eq!(message["params"]["uri"], URI);
+                return message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    fn open(&mut self, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "strata", "version": 1, "text": text }
+            }),
+        );
+    }
+
+    fn change(&mut self, version: i32, text: &str) {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": version },
+                "contentChanges": [{ "text": text }]
+            }),
+        );
+    }
+
+    fn shutdown(mut self) {
+        assert_eq!(self.request("shutdown", Value::Null), Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[test]
+fn lsp_publishes_diagnostics_as_document_changes() {
+    let mut client = Client::start();
+
+    client.open("{\n  retries 3\n  ports: [1, 2\n}\n");
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "expected ':' after key `retries`, found integer 3"
+    );
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 1, "character": 10 }, "end": { "line": 1, "character": 11 } })
+    );
+    assert_eq!(diagnostics[0]["code"], "parse.unexpected_token");
+
+    client.change(2, "{\n  retries: 3\n  ports: [1, 2]\n}\n");
+    assert!(client.diagnostics().is_empty());
+
+    client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert!(client.diagnostics().is_empty());
+
+    client.shutdown();
+}
+
+#[test]
+fn lsp_answers_document_requests() {
+    let mut client = Client::start();
+    let document = json!({ "uri": URI });
+
+    client.open("limits {\n  burst: 10\n  keys: [\n    \"a\",\n  ]\n}\n");
+    assert!(client.diagnostics().is_empty());
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": document, "position": { "line": 1, "character": 10 } }),
+    );
+    let contents = hover["contents"]["value"].as_str().unwrap();
+    assert!(contents.starts_with("**int**\n\nblake3 `"), "{}", contents);
+
+    let folds = client.request(
+        "textDocument/foldingRange",
+        json!({ "textDocument": document }),
+    );
+    assert_eq!(folds.as_array().unwrap().len(), 2);
+
+    let tokens = client.request(
+        "textDocument/semanticTokens/full",
+        json!({ "textDocument": document }),
+    );
+    // five tokens of five numbers each: limits burst 10 keys "a"
+    assert_eq!(tokens["data"].as_array().unwrap().len(), 25);
+
+    let edits = client.request(
+        "textDocument/formatting",
+        json!({ "textDocument": document, "options": { "tabSize": 2, "insertSpaces": true } }),
+    );
+    assert_eq!(
+        edits[0]["newText"],
+        "limits {\n  burst: 10\n  keys: [\"a\"]\n}\n"
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn lsp_survives_malformed_notifications() {
+    let mut client = Client::start();
+
+    client.notify("textDocument/didOpen", json!({ "textDocument": 42 }));
+    client.notify("textDocument/didChange", json!({}));
+
+    client.open("a { b: 1 }\n");
+    assert!(client.diagnostics().is_empty());
+
+    client.shutdown();
+}
@@ -0,0 +1,416 @@
+#![cfg(feature = "derive")]
+
+use std::collections::BTreeMap;
+
+use strata::error::{ConvertError, ConvertErrorKind};
+use strata::value::Value;
+use strata::{FromStrata, ToStrata, bytes, int, list, map, null, string};
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Service {
+    name: String,
+    #[strata(rename = "port-list")]
+    ports: Vec<u16>,
+    timeout: Option<u32>,
+    #[strata(skip_none)]
+    region: Option<String>,
+    #[strata(skip)]
+    connections: u32,
+    #[strata(flatten)]
+    limits: Limits,
+    mode: Mode,
+    key: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, ToStrata, FromStrata)]
+struct Limits {
+    burst: u32,
+    rate: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, ToStrata, FromStrata)]
+enum Mode {
+    Active,
+    Limited(u32),
+    Pair(bool, bool),
+    #[strata(rename = "window")]
+    Window {
+        from: i64,
+        to: i64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, ToStrata, FromStrata)]
+#[strata(tag = "type")]
+enum Event {
+    Started,
+    Moved { x: i64, y: i64 },
+    Resized(Limits),
+}
+
+#[derive(Debug, Clone, PartialEq, ToStrata, FromStrata)]
+#[strata(tag = "t", content = "c")]
+enum Message {
+    Ping,
+    Text(String),
+    Point(i64, i64),
+    Span { from: u8, to: u8 },
+}
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Overridden {
+    name: String,
+    #[strata(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+#[strata(tag = "type")]
+enum Tagged {
+    Extra(BTreeMap<String, Value>),
+}
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Id(u32);
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Pair(i32, String);
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Marker;
+
+#[derive(Debug, PartialEq, ToStrata, FromStrata)]
+struct Labeled<T> {
+    label: String,
+    items: Vec<T>,
+}
+
+fn service() -> Service {
+    Service {
+        name: "api".to_string(),
+        ports: vec![80, 443],
+        timeout: None,
+        region: None,
+        connections: 0,
+        limits: Limits { burst: 10, rate: 5 },
+        mode: Mode::Window { from: -1, to: 1 },
+        key: bytes!(vec![0xde, 0xad]),
+    }
+}
+
+fn service_value() -> Value {
+    map! {
+        "burst" => int!(10),
+        "key" => bytes!(vec![0xde, 0xad]),
+        "mode" => map! { "window" => map! { "from" => int!(-1), "to" => int!(1) } },
+        "name" => string!("api"),
+        "port-list" => list![int!(80), int!(443)],
+        "rate" => int!(5),
+        "timeout" => null!(),
+    }
+}
+
+fn error_at(kind: ConvertErrorKind, path: &str) -> ConvertError {
+    ConvertError {
+        kind,
+        path: path.to_string(),
+    }
+}
+
+fn with(value: Value, key: &str, entry: Value) -> Value {
+    let Value::Map(mut map) = value else {
+        unreachable!()
+    };
+    map.insert(key.to_string(), entry);
+    Value::Map(map)
+}
+
+fn without(value: Value, key: &str) -> Value {
+    let Value::Map(mut map) = value else {
+        unreachable!()
+    };
+    map.remove(key);
+    Value::Map(map)
+}
+
+#[test]
+fn struct_roundtrips() {
+    assert_eq!(Value::from(service()), service_value());
+    assert_eq!(Service::try_from(service_value()).unwrap(), service());
+}
+
+#[test]
+fn optional_fields() {
+    let mut expected = service();
+    expected.timeout = Some(30);
+    expected.region = Some("eu".to_string());
+
+    let value = with(service_value(), "timeout", int!(30));
+    let value = with(value, "region", string!("eu"));
+    assert_eq!(Value::from(expected), value);
+
+    // missing and null both read as None
+    let value = without(service_value(), "timeout");
+    let value = with(value, "region", null!());
+    assert_eq!(Service::try_from(value).unwrap(), service());
+}
+
+#[test]
+fn skipped_fields_use_default() {
+    let mut running = service();
+    running.connections = 12;
+    assert_eq!(Value::from(running), service_value());
+
+    // a key with the skipped field's name is ignored like any other
+    let value = with(service_value(), "connections", int!(3));
+    assert_eq!(Service::try_from(value).unwrap().connections, 0);
+}
+
+#[test]
+fn errors_name_the_field() {
+    let value = without(service_value(), "name");
+    assert_eq!(
+        Service::try_from(value).unwrap_err(),
+        error_at(ConvertErrorKind::MissingField, "name")
+    );
+
+    let value = with(service_value(), "port-list", list![int!(80), int!(70000)]);
+    let err = Service::try_from(value).unwrap_err();
+    assert_eq!(
+        err,
+        error_at(
+            ConvertErrorKind::IntegerOutOfRange(70000, "u16"),
+            "\"port-list\"[1]"
+        )
+    );
+    assert_eq!(
+        err.to_string(),
+        "integer 70000 out of range for u16 at `\"port-list\"[1]`"
+    );
+    assert_eq!(err.code(), "convert.integer_out_of_range");
+
+    // flattened fields are found in the parent map
+    let value = with(service_value(), "rate", string!("fast"));
+    assert_eq!(
+        Service::try_from(value).unwrap_err().to_string(),
+        "expected int, found string at `rate`"
+    );
+
+    let value = with(
+        service_value(),
+        "mode",
+        map! { "window" => map! { "from" => int!(0) } },
+    );
+    assert_eq!(
+        Service::try_from(value).unwrap_err().to_string(),
+        "missing field at `mode.window.to`"
+    );
+
+    assert_eq!(
+        Service::try_from(list![]).unwrap_err().to_string(),
+        "expected map, found list"
+    );
+}
+
+#[test]
+fn externally_tagged_enum() {
+    for (mode, value) in [
+        (Mode::Active, string!("Active")),
+        (Mode::Limited(5), map! { "Limited" => int!(5) }),
+        (
+            Mode::Pair(true, false),
+            map! { "Pair" => list![Value::Bool(true), Value::Bool(false)] },
+        ),
+        (
+            Mode::Window { from: 0, to: 9 },
+            map! { "window" => map! { "from" => int!(0), "to" => int!(9) } },
+        ),
+    ] {
+        assert_eq!(Value::from(mode.clone()), value);
+        assert_eq!(Mode::try_from(value).unwrap(), mode);
+    }
+
+    assert_eq!(
+        Mode::try_from(map! { "Active" => null!() }).unwrap(),
+        Mode::Active
+    );
+    assert_eq!(
+        Mode::try_from(string!("Window")).unwrap_err(),
+        error_at(ConvertErrorKind::UnknownVariant("Window".to_string()), "")
+    );
+    assert_eq!(
+        Mode::try_from(map! { "Pair" => list![Value::Bool(true)] })
+            .unwrap_err()
+            .to_string(),
+        "expected 2 elements, found 1 at `Pair`"
+    );
+    assert_eq!(
+        Mode::try_from(string!("Limited")).unwrap_err().to_string(),
+        "expected map, found string"
+    );
+}
+
+#[test]
+fn internally_tagged_enum() {
+    for (event, value) in [
+        (Event::Started, map! { "type" => string!("Started") }),
+        (
+            Event::Moved { x: 1, y: 2 },
+            map! { "type" => string!("Moved"), "x" => int!(1), "y" => int!(2) },
+        ),
+        (
+            Event::Resized(Limits { burst: 1, rate: 2 }),
+            map! { "burst" => int!(1), "rate" => int!(2), "type" => string!("Resized") },
+        ),
+    ] {
+        assert_eq!(Value::from(event.clone()), value);
+        assert_eq!(Event::try_from(value).unwrap(), event);
+    }
+
+    assert_eq!(
+        Event::try_from(map! { "type" => string!("Moved"), "x" => int!(1) }).unwrap_err(),
+        error_at(ConvertErrorKind::MissingField, "y")
+    );
+    assert_eq!(
+        Event::try_from(map! { "type" => string!("Stopped") })
+            .unwrap_err()
+            .to_string(),
+        "unknown variant `Stopped` at `type`"
+    );
+    assert_eq!(
+        Event::try_from(map! {}).unwrap_err(),
+        error_at(ConvertErrorKind::MissingField, "type")
+    );
+}
+
+#[test]
+fn adjacently_tagged_enum() {
+    for (message, value) in [
+        (Message::Ping, map! { "t" => string!("Ping") }),
+        (
+            Message::Text("hi".to_string()),
+            map! { "c" => string!("hi"), "t" => string!("Text") },
+        ),
+        (
+            Message::Point(1, 2),
+            map! { "c" => list![int!(1), int!(2)], "t" => string!("Point") },
+        ),
+        (
+            Message::Span { from: 1, to: 2 },
+            map! { "c" => map! { "from" => int!(1), "to" => int!(2) }, "t" => string!("Span") },
+        ),
+    ] {
+        assert_eq!(Value::from(message.clone()), value);
+        assert_eq!(Message::try_from(value).unwrap(), message);
+    }
+
+    assert_eq!(
+        Message::try_from(
+            map! { "c" => map! { "from" => int!(1), "to" => int!(-2) }, "t" => string!("Span") }
+        )
+        .unwrap_err()
+        .to_string(),
+        "integer -2 out of range for u8 at `c.to`"
+    );
+    assert_eq!(
+        Message::try_from(map! { "t" => string!("Text") }).unwrap_err(),
+        error_at(ConvertErrorKind::MissingField, "c")
+    );
+}
+
+#[test]
+fn tuple_newtype_and_unit_structs() {
+    assert_eq!(Value::from(Id(7)), int!(7));
+    assert_eq!(Id::try_from(int!(7)).unwrap(), Id(7));
+    assert_eq!(
+        Id::try_from(int!(-1)).unwrap_err(),
+        error_at(ConvertErrorKind::IntegerOutOfRange(-1, "u32"), "")
+    );
+
+    let pair = Pair(1, "one".to_string());
+    assert_eq!(Value::from(pair), list![int!(1), string!("one")]);
+    assert_eq!(
+        Pair::try_from(list![int!(1), int!(1)])
+            .unwrap_err()
+            .to_string(),
+        "expected string, found int at `[1]`"
+    );
+
+    assert_eq!(Value::from(Marker), null!());
+    assert_eq!(Marker::try_from(null!()).unwrap(), Marker);
+}
+
+#[test]
+fn generic_structs() {
+    let labeled = Labeled {
+        label: "ids".to_string(),
+        items: vec![Id(1), Id(2)],
+    };
+    let value = map! { "items" => list![int!(1), int!(2)], "label" => string!("ids") };
+
+    assert_eq!(Value::from(labeled), value);
+    assert_eq!(
+        Labeled::<Id>::try_from(value).unwrap().items,
+        vec![Id(1), Id(2)]
+    );
+}
+
+#[test]
+fn maps_of_derived_types() {
+    let limits = BTreeMap::from([("eu".to_string(), Limits { burst: 1, rate: 2 })]);
+    let value = map! { "eu" => map! { "burst" => int!(1), "rate" => int!(2) } };
+    assert_eq!(Value::from(limits), value);
+
+    let value = map! { "eu" => map! { "burst" => int!(1) }, "us west" => null!() };
+    assert_eq!(
+        BTreeMap::<String, Limits>::try_from(value)
+            .unwrap_err()
+            .to_string(),
+        "missing field at `eu.rate`"
+    );
+}
+
+#[test]
+fn flattened_field_takes_remaining_keys() {
+    let value = map! { "burst" => int!(1), "name" => string!("api"), "rate" => int!(2) };
+    let overridden = Overridden::try_from(value.clone()).unwrap();
+
+    assert_eq!(
+        overridden.extra,
+        BTreeMap::from([
+            ("burst".to_string(), int!(1)),
+            ("rate".to_string(), int!(2))
+        ])
+    );
+    assert_eq!(Value::from(overridden), value);
+}
+
+#[test]
+fn fields_win_over_flattened_keys() {
+    let value = Value::from(Overridden {
+        name: "api".to_string(),
+        extra: BTreeMap::from([
+            ("name".to_string(), string!("other")),
+            ("rate".to_string(), int!(3)),
+        ]),
+    });
+
+    assert_eq!(value, map! { "name" => string!("api"), "rate" => int!(3) });
+}
+
+#[test]
+fn tag_wins_over_variant_content() {
+    let value = Value::from(Tagged::Extra(BTreeMap::from([
+        ("type".to_string(), string!("Other")),
+        ("size".to_string(), int!(1)),
+    ])));
+
+    assert_eq!(
+        value,
+        map! { "size" => int!(1), "type" => string!("Extra") }
+    );
+    assert_eq!(
+        Tagged::try_from(value).unwrap(),
+        Tagged::Extra(BTreeMap::from([("size".to_string(), int!(1))]))
+    );
+}